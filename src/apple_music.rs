@@ -18,8 +18,10 @@ impl From<Track> for UnifiedTrack {
         UnifiedTrack {
             artist: val.artist,
             title: val.title,
-            progress: val.progress,
-            duration: val.duration,
+            progress: Some(val.progress),
+            duration: Some(val.duration),
+            paused: !matches!(val.player_state, PlayerState::Playing),
+            ..Default::default()
         }
     }
 }
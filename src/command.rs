@@ -0,0 +1,80 @@
+use std::{process::Stdio, time::Duration};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::UnifiedTrack;
+
+#[derive(Debug, Deserialize)]
+pub struct Track {
+    #[serde(default)]
+    pub artist: String,
+    pub title: String,
+    pub progress_ms: Option<u64>,
+    pub duration_ms: Option<u64>,
+    #[serde(default = "default_playing")]
+    pub playing: bool,
+}
+
+fn default_playing() -> bool {
+    true
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: val.progress_ms.map(Duration::from_millis),
+            duration: val.duration_ms.map(Duration::from_millis),
+            paused: !val.playing,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Client {
+    argv: Vec<String>,
+    timeout: Duration,
+}
+
+impl Client {
+    pub fn new(argv: Vec<String>, timeout: Duration) -> anyhow::Result<Self> {
+        if argv.is_empty() {
+            anyhow::bail!("command is empty");
+        }
+        Ok(Self { argv, timeout })
+    }
+
+    /// Runs the command and parses its stdout. A non-zero exit code or an empty
+    /// output means that nothing is playing.
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        let child = tokio::process::Command::new(&self.argv[0])
+            .args(&self.argv[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            // dropping the future on timeout kills the child
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("error running {}", self.argv[0]))?;
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .with_context(|| format!("{} timed out after {:?}", self.argv[0], self.timeout))??;
+
+        if !output.status.success() {
+            log::debug!("{} exited with {}", self.argv[0], output.status);
+            return Ok(None);
+        }
+
+        let stdout = String::from_utf8(output.stdout).context("command output is not utf-8")?;
+        if stdout.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            serde_json::from_str(&stdout).context("error parsing command output")?,
+        ))
+    }
+}
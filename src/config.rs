@@ -1,14 +1,51 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    proxy::ProxyConfig,
+    schedule::ScheduleConfig,
+    template::{Bar, Template},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub service: Service,
+    /// Sources in order of priority, a single source is accepted as well.
+    #[serde(deserialize_with = "one_or_many")]
+    pub service: Vec<Service>,
     pub interval: u64,
-    pub template: String,
+    /// Seconds between requests to the sources, `interval` when unset. The
+    /// progress is extrapolated on the ticks in between.
+    #[serde(default)]
+    pub poll_interval: Option<u64>,
+    /// Polls right after the current track ends instead of every `interval`.
+    #[serde(default)]
+    pub schedule: Option<ScheduleConfig>,
+    pub template: Template,
+    /// Template for paused tracks, `template` is used when unset.
+    #[serde(default)]
+    pub paused_template: Option<Template>,
+    /// Seconds a track may stay paused before `default` is shown instead.
+    #[serde(default)]
+    pub paused_timeout: Option<u64>,
     pub default: String,
-    pub telegram: TelegramConfig,
+    /// Maximum length of the text, detected from the account when unset.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// Most updates each output may send in an hour, unlimited when unset.
+    #[serde(default)]
+    pub max_updates_per_hour: Option<u32>,
+    #[serde(default)]
+    pub bar: Bar,
+    /// Proxy for Telegram and Spotify.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Proxies for single sources by their name, used instead of `proxy`.
+    #[serde(default)]
+    pub proxies: HashMap<String, ProxyConfig>,
+    /// Where the text goes, a single output is accepted as well.
+    #[serde(deserialize_with = "one_or_many")]
+    pub telegram: Vec<OutputConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +55,177 @@ pub enum Service {
     #[cfg(target_os = "macos")]
     #[serde(rename = "apple_music")]
     AppleMusic,
+    #[cfg(target_os = "linux")]
+    #[serde(rename = "mpris")]
+    Mpris {
+        #[serde(default)]
+        player: Option<String>,
+    },
+    #[serde(rename = "mpd")]
+    Mpd {
+        #[serde(default = "default_mpd_host")]
+        host: String,
+        #[serde(default = "default_mpd_port")]
+        port: u16,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    #[serde(rename = "lastfm")]
+    LastFm {
+        api_key: String,
+        username: String,
+        #[serde(default = "default_lastfm_api_url")]
+        api_url: String,
+    },
+    #[serde(rename = "listenbrainz")]
+    ListenBrainz {
+        #[serde(default)]
+        token: Option<String>,
+        username: String,
+        #[serde(default = "default_listenbrainz_api_root")]
+        api_root: String,
+    },
+    #[serde(rename = "jellyfin")]
+    Jellyfin {
+        url: String,
+        api_key: String,
+        username: String,
+    },
+    #[serde(rename = "emby")]
+    Emby {
+        url: String,
+        api_key: String,
+        username: String,
+    },
+    #[serde(rename = "subsonic")]
+    Subsonic {
+        url: String,
+        username: String,
+        password: String,
+    },
+    #[serde(rename = "plex")]
+    Plex {
+        url: String,
+        token: String,
+        username: String,
+    },
+    #[serde(rename = "vlc")]
+    Vlc {
+        #[serde(default = "default_vlc_url")]
+        url: String,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    #[serde(rename = "command")]
+    Command {
+        argv: Vec<String>,
+        #[serde(default = "default_command_timeout")]
+        timeout: u64,
+    },
+    #[serde(rename = "push")]
+    Push {
+        #[serde(default = "default_push_address")]
+        address: String,
+        token: String,
+        #[serde(default = "default_push_expiry")]
+        expiry: u64,
+    },
+    #[serde(rename = "file")]
+    File {
+        path: PathBuf,
+        #[serde(default = "default_file_format")]
+        format: String,
+    },
+}
+
+impl Service {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Spotify => "spotify",
+            #[cfg(target_os = "macos")]
+            Service::AppleMusic => "apple_music",
+            #[cfg(target_os = "linux")]
+            Service::Mpris { .. } => "mpris",
+            Service::Mpd { .. } => "mpd",
+            Service::LastFm { .. } => "lastfm",
+            Service::ListenBrainz { .. } => "listenbrainz",
+            Service::Jellyfin { .. } => "jellyfin",
+            Service::Emby { .. } => "emby",
+            Service::Subsonic { .. } => "subsonic",
+            Service::Plex { .. } => "plex",
+            Service::Vlc { .. } => "vlc",
+            Service::Command { .. } => "command",
+            Service::Push { .. } => "push",
+            Service::File { .. } => "file",
+        }
+    }
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    // going through a value instead of an untagged enum keeps the inner error messages
+    let value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Array(_) => Vec::<T>::deserialize(value),
+        _ => T::deserialize(value).map(|value| vec![value]),
+    }
+    .map_err(serde::de::Error::custom)
+}
+
+fn default_mpd_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_mpd_port() -> u16 {
+    6600
+}
+
+fn default_lastfm_api_url() -> String {
+    crate::lastfm::DEFAULT_API_URL.to_string()
+}
+
+fn default_listenbrainz_api_root() -> String {
+    crate::listenbrainz::DEFAULT_API_ROOT.to_string()
+}
+
+fn default_vlc_url() -> String {
+    crate::vlc::DEFAULT_URL.to_string()
+}
+
+fn default_command_timeout() -> u64 {
+    5
+}
+
+fn default_push_address() -> String {
+    crate::push::DEFAULT_ADDRESS.to_string()
+}
+
+fn default_push_expiry() -> u64 {
+    600
+}
+
+fn default_file_format() -> String {
+    crate::file::DEFAULT_FORMAT.to_string()
+}
+
+/// An updater together with settings that override the global ones for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    #[serde(flatten)]
+    pub telegram: TelegramConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<Template>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_template: Option<Template>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_updates_per_hour: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,14 +245,31 @@ pub enum TelegramConfig {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            service: Service::Spotify,
+            service: vec![Service::Spotify],
             interval: 60,
-            template: "{artist} — {title} [{progress} / {duration}]".to_string(),
+            poll_interval: None,
+            schedule: None,
+            template: Template::parse("{artist} — {title} [{progress} / {duration}]")
+                .expect("invalid default template"),
+            paused_template: None,
+            paused_timeout: None,
             default: "nothing playing".to_string(),
-            telegram: TelegramConfig::Bio {
-                api_id: 123456789,
-                api_hash: "".to_string(),
-            },
+            max_length: None,
+            max_updates_per_hour: None,
+            bar: Bar::default(),
+            proxy: None,
+            proxies: HashMap::new(),
+            telegram: vec![OutputConfig {
+                telegram: TelegramConfig::Bio {
+                    api_id: 123456789,
+                    api_hash: "".to_string(),
+                },
+                template: None,
+                paused_template: None,
+                max_length: None,
+                max_updates_per_hour: None,
+                proxy: None,
+            }],
         }
     }
 }
@@ -0,0 +1,150 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::UnifiedTrack;
+
+pub const DEFAULT_FORMAT: &str = "{artist} - {title}";
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub artist: String,
+    pub title: String,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: None,
+            duration: None,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// A pattern like `{artist} - {title}`. Every placeholder captures the text up
+/// to the next literal, unknown placeholders are matched but ignored.
+#[derive(Debug)]
+pub struct Format(Vec<Part>);
+
+impl Format {
+    pub fn parse(format: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').context("unclosed placeholder in format")? + start;
+            if matches!(parts.last(), Some(Part::Placeholder(_))) {
+                anyhow::bail!("placeholders in format must be separated by text");
+            }
+            parts.push(Part::Placeholder(rest[start + 1..end].to_string()));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        Ok(Self(parts))
+    }
+
+    /// Returns `None` when the text does not match the pattern.
+    pub fn apply(&self, text: &str) -> Option<Track> {
+        let mut track = Track {
+            artist: String::new(),
+            title: String::new(),
+        };
+
+        let mut rest = text;
+        for (i, part) in self.0.iter().enumerate() {
+            match part {
+                Part::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                Part::Placeholder(name) => {
+                    let value = match self.0.get(i + 1) {
+                        Some(Part::Literal(next)) => {
+                            let end = rest.find(next.as_str())?;
+                            let value = &rest[..end];
+                            rest = &rest[end..];
+                            value
+                        }
+                        _ => std::mem::take(&mut rest),
+                    };
+                    match name.as_str() {
+                        "artist" => track.artist = value.trim().to_string(),
+                        "title" => track.title = value.trim().to_string(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        rest.is_empty().then_some(track)
+    }
+}
+
+fn read(path: &Path, format: &Format) -> Option<Track> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    // text that does not follow the pattern is still worth showing
+    Some(format.apply(text).unwrap_or_else(|| Track {
+        artist: String::new(),
+        title: text.to_string(),
+    }))
+}
+
+pub struct Client {
+    track: Arc<Mutex<Option<Track>>>,
+    // kept alive for as long as the client is
+    _watcher: RecommendedWatcher,
+}
+
+impl Client {
+    /// Reads the file once and then re-reads it whenever it changes. The parent
+    /// directory is watched, so the file may be missing or replaced at any time.
+    pub fn new(path: PathBuf, format: &str) -> anyhow::Result<Self> {
+        let format = Format::parse(format)?;
+        let path = std::path::absolute(path).context("error resolving path")?;
+        let directory = path.parent().context("path has no parent directory")?.to_path_buf();
+
+        let track = Arc::new(Mutex::new(read(&path, &format)));
+
+        let watcher_track = track.clone();
+        let watched_path = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.paths.contains(&watched_path) => {
+                *watcher_track.lock().unwrap() = read(&watched_path, &format);
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("error watching {}: {e}", watched_path.display()),
+        })
+        .context("error creating watcher")?;
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .with_context(|| format!("error watching {}", directory.display()))?;
+
+        Ok(Self {
+            track,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn get_current_track(&self) -> Option<Track> {
+        self.track.lock().unwrap().clone()
+    }
+}
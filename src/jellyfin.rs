@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{PlayingFirst, UnifiedTrack};
+
+/// Jellyfin is a fork of Emby and keeps its sessions API, only the
+/// authorization header and the path prefix differ.
+#[derive(Debug, Clone, Copy)]
+pub enum Server {
+    Jellyfin,
+    Emby,
+}
+
+impl Server {
+    fn sessions_path(self) -> &'static str {
+        match self {
+            Self::Jellyfin => "/Sessions",
+            Self::Emby => "/emby/Sessions",
+        }
+    }
+
+    fn auth_header(self, api_key: &str) -> (&'static str, String) {
+        match self {
+            Self::Jellyfin => ("Authorization", format!("MediaBrowser Token=\"{api_key}\"")),
+            Self::Emby => ("X-Emby-Token", api_key.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Track {
+    pub paused: bool,
+    pub artists: Vec<String>,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artists.join(", "),
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            paused: val.paused,
+            ..Default::default()
+        }
+    }
+}
+
+// positions and lengths are reported in 100ns ticks
+fn from_ticks(ticks: i64) -> Duration {
+    Duration::from_nanos(ticks.max(0) as u64 * 100)
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    server: Server,
+    url: String,
+    api_key: String,
+    username: String,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, server: Server, url: String, api_key: String, username: String) -> Self {
+        Self {
+            client,
+            server,
+            url,
+            api_key,
+            username,
+        }
+    }
+
+    /// Returns the track of one of the user's audio sessions, picked with [`PlayingFirst`].
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Session {
+            user_name: Option<String>,
+            now_playing_item: Option<Item>,
+            #[serde(default)]
+            play_state: PlayState,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Item {
+            name: String,
+            #[serde(default)]
+            artists: Vec<String>,
+            album_artist: Option<String>,
+            media_type: Option<String>,
+            run_time_ticks: Option<i64>,
+        }
+
+        #[derive(Deserialize, Default)]
+        #[serde(rename_all = "PascalCase")]
+        struct PlayState {
+            position_ticks: Option<i64>,
+            #[serde(default)]
+            is_paused: bool,
+        }
+
+        let (header, value) = self.server.auth_header(&self.api_key);
+        let sessions: Vec<Session> = self
+            .client
+            .get(format!(
+                "{}{}",
+                self.url.trim_end_matches('/'),
+                self.server.sessions_path()
+            ))
+            .header(header, value)
+            .send()
+            .await
+            .context("error doing request")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("error parsing sessions")?;
+
+        let mut found = PlayingFirst::default();
+        for session in sessions {
+            if !session
+                .user_name
+                .is_some_and(|name| name.eq_ignore_ascii_case(&self.username))
+            {
+                continue;
+            }
+            let Some(item) = session.now_playing_item else {
+                continue;
+            };
+            if item.media_type.as_deref() != Some("Audio") {
+                continue;
+            }
+
+            let track = Track {
+                paused: session.play_state.is_paused,
+                artists: if item.artists.is_empty() {
+                    item.album_artist.into_iter().collect()
+                } else {
+                    item.artists
+                },
+                title: item.name,
+                progress: from_ticks(session.play_state.position_ticks.unwrap_or_default()),
+                duration: item.run_time_ticks.map(from_ticks),
+            };
+
+            let paused = track.paused;
+            if found.offer(track, paused) {
+                break;
+            }
+        }
+
+        Ok(found.into_inner())
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::UnifiedTrack;
+
+pub const DEFAULT_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+#[derive(Debug)]
+pub struct Track {
+    pub artist: String,
+    pub title: String,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: None,
+            duration: None,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    username: String,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, api_url: String, api_key: String, username: String) -> Self {
+        Self {
+            client,
+            api_url,
+            api_key,
+            username,
+        }
+    }
+
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Response {
+            Ok { recenttracks: RecentTracks },
+            Error { error: u32, message: String },
+        }
+
+        #[derive(Deserialize)]
+        struct RecentTracks {
+            #[serde(default)]
+            track: OneOrMany<RecentTrack>,
+        }
+
+        // last.fm returns a bare object instead of an array when there is a single track
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany<T> {
+            One(T),
+            Many(Vec<T>),
+        }
+
+        impl<T> Default for OneOrMany<T> {
+            fn default() -> Self {
+                Self::Many(Vec::new())
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct RecentTrack {
+            name: String,
+            artist: Text,
+            #[serde(rename = "@attr")]
+            attr: Option<Attr>,
+        }
+
+        #[derive(Deserialize)]
+        struct Text {
+            #[serde(rename = "#text")]
+            text: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Attr {
+            nowplaying: String,
+        }
+
+        let response: Response = self
+            .client
+            .get(&self.api_url)
+            .query(&[
+                ("method", "user.getrecenttracks"),
+                ("user", &self.username),
+                ("api_key", &self.api_key),
+                ("format", "json"),
+                ("limit", "1"),
+            ])
+            .send()
+            .await
+            .context("error doing request")?
+            .json()
+            .await
+            .context("error parsing recent tracks")?;
+
+        let tracks = match response {
+            Response::Ok { recenttracks } => match recenttracks.track {
+                OneOrMany::One(track) => vec![track],
+                OneOrMany::Many(tracks) => tracks,
+            },
+            Response::Error { error, message } => anyhow::bail!("last.fm error {error}: {message}"),
+        };
+
+        Ok(tracks
+            .into_iter()
+            .find(|track| track.attr.as_ref().is_some_and(|attr| attr.nowplaying == "true"))
+            .map(|track| Track {
+                artist: track.artist.text,
+                title: track.name,
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::{self, JoinHandle};
+
+    use tiny_http::{Response, Server};
+
+    use super::*;
+
+    /// Answers a single request with `body` and `status`, the handle yields the requested url.
+    fn serve_once(status: u16, body: &'static str) -> (String, JoinHandle<String>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let api_url = format!("http://{}/2.0/", server.server_addr());
+        let handle = thread::spawn(move || {
+            let request = server.recv().unwrap();
+            let url = request.url().to_string();
+            request
+                .respond(Response::from_string(body).with_status_code(status))
+                .unwrap();
+            url
+        });
+        (api_url, handle)
+    }
+
+    async fn get_current_track(status: u16, body: &'static str) -> anyhow::Result<Option<Track>> {
+        let (api_url, handle) = serve_once(status, body);
+        let client = Client::new(
+            reqwest::Client::new(),
+            api_url,
+            "key".to_string(),
+            "someone".to_string(),
+        );
+        let track = client.get_current_track().await;
+
+        let url = handle.join().unwrap();
+        assert!(url.starts_with("/2.0/?"), "{url}");
+        for parameter in [
+            "method=user.getrecenttracks",
+            "user=someone",
+            "api_key=key",
+            "format=json",
+        ] {
+            assert!(url.contains(parameter), "{url} has no {parameter}");
+        }
+        track
+    }
+
+    #[tokio::test]
+    async fn reads_single_track_object() {
+        let track = get_current_track(
+            200,
+            r##"{"recenttracks": {"@attr": {"user": "someone", "total": "1"}, "track": {
+                "name": "Title",
+                "artist": {"mbid": "", "#text": "Artist"},
+                "@attr": {"nowplaying": "true"}
+            }}}"##,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(track.title, "Title");
+    }
+
+    #[tokio::test]
+    async fn picks_now_playing_from_list() {
+        let track = get_current_track(
+            200,
+            r##"{"recenttracks": {"track": [
+                {"name": "Earlier", "artist": {"#text": "Someone Else"}, "date": {"uts": "1700000000"}},
+                {"name": "Title", "artist": {"#text": "Artist"}, "@attr": {"nowplaying": "true"}},
+                {"name": "Even Earlier", "artist": {"#text": "Another"}, "@attr": {"nowplaying": "false"}}
+            ]}}"##,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(track.title, "Title");
+    }
+
+    #[tokio::test]
+    async fn returns_nothing_without_now_playing() {
+        let track = get_current_track(
+            200,
+            r##"{"recenttracks": {"track": [
+                {"name": "Earlier", "artist": {"#text": "Artist"}, "date": {"uts": "1700000000"}}
+            ]}}"##,
+        )
+        .await
+        .unwrap();
+
+        assert!(track.is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_api_error() {
+        let error = get_current_track(403, r##"{"error": 10, "message": "Invalid API key"}"##)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "last.fm error 10: Invalid API key");
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::UnifiedTrack;
+
+pub const DEFAULT_API_ROOT: &str = "https://api.listenbrainz.org";
+
+#[derive(Debug)]
+pub struct Track {
+    pub artist: String,
+    pub title: String,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: None,
+            duration: val.duration,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    api_root: String,
+    token: Option<String>,
+    username: String,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, api_root: String, token: Option<String>, username: String) -> Self {
+        Self {
+            client,
+            api_root,
+            token,
+            username,
+        }
+    }
+
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        #[derive(Deserialize)]
+        struct Response {
+            payload: Payload,
+        }
+
+        #[derive(Deserialize)]
+        struct Payload {
+            #[serde(default)]
+            listens: Vec<Listen>,
+        }
+
+        #[derive(Deserialize)]
+        struct Listen {
+            track_metadata: TrackMetadata,
+        }
+
+        #[derive(Deserialize)]
+        struct TrackMetadata {
+            artist_name: String,
+            track_name: String,
+            #[serde(default)]
+            additional_info: AdditionalInfo,
+        }
+
+        #[derive(Deserialize, Default)]
+        struct AdditionalInfo {
+            duration_ms: Option<u64>,
+            duration: Option<u64>,
+        }
+
+        let url = format!(
+            "{}/1/user/{}/playing-now",
+            self.api_root.trim_end_matches('/'),
+            self.username
+        );
+        let mut request = self.client.get(url);
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {token}"));
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("error doing request")?
+            .error_for_status()?;
+        let response: Response = response.json().await.context("error parsing playing-now response")?;
+
+        Ok(response.payload.listens.into_iter().next().map(|listen| {
+            let metadata = listen.track_metadata;
+            let info = metadata.additional_info;
+            Track {
+                artist: metadata.artist_name,
+                title: metadata.track_name,
+                duration: info
+                    .duration_ms
+                    .map(Duration::from_millis)
+                    .or(info.duration.map(Duration::from_secs)),
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::{self, JoinHandle};
+
+    use tiny_http::{Response, Server};
+
+    use super::*;
+
+    /// Answers a single request with `body`, the handle yields the requested url and the `Authorization` header.
+    fn serve_once(body: &'static str) -> (String, JoinHandle<(String, Option<String>)>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let api_root = format!("http://{}", server.server_addr());
+        let handle = thread::spawn(move || {
+            let request = server.recv().unwrap();
+            let url = request.url().to_string();
+            let authorization = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .map(|header| header.value.to_string());
+            request.respond(Response::from_string(body)).unwrap();
+            (url, authorization)
+        });
+        (api_root, handle)
+    }
+
+    async fn get_current_track(body: &'static str, token: Option<&str>) -> (Option<Track>, String, Option<String>) {
+        let (api_root, handle) = serve_once(body);
+        let client = Client::new(
+            reqwest::Client::new(),
+            api_root,
+            token.map(str::to_string),
+            "someone".to_string(),
+        );
+        let track = client.get_current_track().await.unwrap();
+        let (url, authorization) = handle.join().unwrap();
+        (track, url, authorization)
+    }
+
+    #[tokio::test]
+    async fn reads_duration_ms() {
+        let (track, url, authorization) = get_current_track(
+            r#"{"payload": {"count": 1, "playing_now": true, "user_id": "someone", "listens": [{
+                "playing_now": true,
+                "track_metadata": {
+                    "artist_name": "Artist",
+                    "track_name": "Title",
+                    "additional_info": {"duration_ms": 215500, "duration": 999}
+                }
+            }]}}"#,
+            Some("secret"),
+        )
+        .await;
+
+        let track = track.unwrap();
+        assert_eq!(url, "/1/user/someone/playing-now");
+        assert_eq!(authorization.as_deref(), Some("Token secret"));
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(track.title, "Title");
+        assert_eq!(track.duration, Some(Duration::from_millis(215_500)));
+    }
+
+    #[tokio::test]
+    async fn reads_duration_in_seconds() {
+        let (track, _, authorization) = get_current_track(
+            r#"{"payload": {"listens": [{"track_metadata": {
+                "artist_name": "Artist",
+                "track_name": "Title",
+                "additional_info": {"duration": 180}
+            }}]}}"#,
+            None,
+        )
+        .await;
+
+        assert_eq!(authorization, None);
+        assert_eq!(track.unwrap().duration, Some(Duration::from_secs(180)));
+    }
+
+    #[tokio::test]
+    async fn allows_missing_duration() {
+        let (track, _, _) = get_current_track(
+            r#"{"payload": {"listens": [{"track_metadata": {"artist_name": "Artist", "track_name": "Title"}}]}}"#,
+            None,
+        )
+        .await;
+
+        let track = track.unwrap();
+        assert_eq!(track.title, "Title");
+        assert_eq!(track.duration, None);
+    }
+
+    #[tokio::test]
+    async fn returns_nothing_without_listens() {
+        let (track, _, _) = get_current_track(
+            r#"{"payload": {"count": 0, "playing_now": true, "user_id": "someone", "listens": []}}"#,
+            None,
+        )
+        .await;
+
+        assert!(track.is_none());
+    }
+}
@@ -1,24 +1,98 @@
 use async_trait::async_trait;
 use config::{Config, Service, TelegramConfig};
 use log::LevelFilter;
+use proxy::ProxyConfig;
 use simplelog::TermLogger;
 use spotify::{Client, FileTokenStorage};
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use telegram::Updater;
+use template::Template;
 #[cfg(target_os = "macos")]
 mod apple_music;
+mod command;
 mod config;
+mod file;
+mod jellyfin;
+mod lastfm;
+mod listenbrainz;
+mod mpd;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod plex;
+mod proxy;
+mod push;
+mod rate_limit;
+mod schedule;
 mod spotify;
+mod subsonic;
 mod telegram;
-#[derive(Debug)]
+mod template;
+mod vlc;
+#[derive(Debug, Default, Clone)]
 pub struct UnifiedTrack {
     artist: String,
     title: String,
-    progress: Duration,
-    duration: Duration,
+    progress: Option<Duration>,
+    duration: Option<Duration>,
+    kind: TrackKind,
+    album: Option<String>,
+    album_artist: Option<String>,
+    year: Option<u16>,
+    url: Option<String>,
+    cover_url: Option<String>,
+    /// Identifier of the track in the source, e.g. the Spotify track ID.
+    id: Option<String>,
+    explicit: bool,
+    paused: bool,
+    /// Name of the service the track came from, filled in by [`FallbackProvider`].
+    source: &'static str,
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+    #[default]
+    Track,
+    Episode,
+}
+impl TrackKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrackKind::Track => "track",
+            TrackKind::Episode => "episode",
+        }
+    }
+}
+/// Picks one of several tracks: the first playing one, or else the first paused one.
+pub struct PlayingFirst<T> {
+    found: Option<(T, bool)>,
+}
+impl<T> Default for PlayingFirst<T> {
+    fn default() -> Self {
+        Self { found: None }
+    }
+}
+impl<T> PlayingFirst<T> {
+    /// Considers the next candidate. Returns `true` once a playing one is found,
+    /// the rest can't win and needn't be looked at.
+    pub fn offer(&mut self, candidate: T, paused: bool) -> bool {
+        if self
+            .found
+            .as_ref()
+            .is_none_or(|(_, found_paused)| *found_paused && !paused)
+        {
+            self.found = Some((candidate, paused));
+        }
+        !paused
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.found.map(|(candidate, _)| candidate)
+    }
 }
 #[async_trait]
-pub trait TrackProvider {
+pub trait TrackProvider: Send {
     async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>>;
 }
 pub struct SpotifyProvider {
@@ -65,25 +139,328 @@ impl TrackProvider for AppleMusicProvider {
         self.script
             .get_current_track()
             .map_err(|e| e.into())
-            .map(|track| Some(track.into()))
+            .map(|track| match track.player_state {
+                apple_music::PlayerState::Stopped => None,
+                _ => Some(track.into()),
+            })
     }
 }
-#[tokio::main]
-async fn main() {
-    TermLogger::init(
-        LevelFilter::Info,
-        simplelog::ConfigBuilder::new()
-            .add_filter_allow_str(module_path!())
-            .build(),
-        simplelog::TerminalMode::Mixed,
-        simplelog::ColorChoice::Auto,
-    )
-    .unwrap();
+#[cfg(target_os = "linux")]
+pub struct MprisProvider {
+    client: mpris::Client,
+}
 
-    let config = Config::load_or_create(PathBuf::from("config.json")).expect("failed to load config");
-    let mut track_provider: Box<dyn TrackProvider> = match config.service {
+#[cfg(target_os = "linux")]
+impl MprisProvider {
+    pub fn new(client: mpris::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[async_trait]
+impl TrackProvider for MprisProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct MpdProvider {
+    client: mpd::Client,
+}
+impl MpdProvider {
+    pub fn new(client: mpd::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for MpdProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct LastFmProvider {
+    client: lastfm::Client,
+}
+impl LastFmProvider {
+    pub fn new(client: lastfm::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for LastFmProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct ListenBrainzProvider {
+    client: listenbrainz::Client,
+}
+impl ListenBrainzProvider {
+    pub fn new(client: listenbrainz::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for ListenBrainzProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct JellyfinProvider {
+    client: jellyfin::Client,
+}
+impl JellyfinProvider {
+    pub fn new(client: jellyfin::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for JellyfinProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct SubsonicProvider {
+    client: subsonic::Client,
+}
+impl SubsonicProvider {
+    pub fn new(client: subsonic::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for SubsonicProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct PlexProvider {
+    client: plex::Client,
+}
+impl PlexProvider {
+    pub fn new(client: plex::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for PlexProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct VlcProvider {
+    client: vlc::Client,
+}
+impl VlcProvider {
+    pub fn new(client: vlc::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for VlcProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct CommandProvider {
+    client: command::Client,
+}
+impl CommandProvider {
+    pub fn new(client: command::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for CommandProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        self.client
+            .get_current_track()
+            .await
+            .map(|track| track.map(|track| track.into()))
+    }
+}
+pub struct PushProvider {
+    client: push::Client,
+}
+impl PushProvider {
+    pub fn new(client: push::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for PushProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        Ok(self.client.get_current_track().map(|track| track.into()))
+    }
+}
+pub struct FileProvider {
+    client: file::Client,
+}
+impl FileProvider {
+    pub fn new(client: file::Client) -> Self {
+        Self { client }
+    }
+}
+#[async_trait]
+impl TrackProvider for FileProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        Ok(self.client.get_current_track().map(|track| track.into()))
+    }
+}
+/// How long a single provider may take before it is skipped like a failed one,
+/// longer than [`proxy::REQUEST_TIMEOUT`] so a hung request fails on its own.
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(15);
+/// Asks the providers in order and picks a track with [`PlayingFirst`]. A
+/// provider that fails is skipped, the chain only fails when every provider does.
+pub struct FallbackProvider {
+    providers: Vec<(&'static str, Box<dyn TrackProvider>)>,
+    last_source: Option<&'static str>,
+}
+impl FallbackProvider {
+    pub fn new(providers: Vec<(&'static str, Box<dyn TrackProvider>)>) -> Self {
+        Self {
+            providers,
+            last_source: None,
+        }
+    }
+}
+#[async_trait]
+impl TrackProvider for FallbackProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        let mut found = PlayingFirst::default();
+        let mut failed = 0;
+        let mut last_error = None;
+
+        for (name, provider) in &mut self.providers {
+            let result = tokio::time::timeout(PROVIDER_TIMEOUT, provider.get_current_track())
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {PROVIDER_TIMEOUT:?}")));
+            match result {
+                Ok(Some(mut track)) => {
+                    track.source = name;
+                    let paused = track.paused;
+                    if found.offer(track, paused) {
+                        break;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("failed to get current track from {name}: {e}");
+                    failed += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let found = found.into_inner();
+        if let Some(e) = last_error.filter(|_| found.is_none() && failed == self.providers.len()) {
+            return Err(e);
+        }
+
+        let source = found.as_ref().map(|track| track.source);
+        if source != self.last_source {
+            match source {
+                Some(name) => log::info!("playing from {name}"),
+                None => log::info!("nothing is playing in any source"),
+            }
+            self.last_source = source;
+        }
+
+        Ok(found)
+    }
+}
+/// Calls the wrapped provider only when the [`Scheduler`](schedule::Scheduler)
+/// says so and extrapolates the progress of a playing track in between.
+pub struct InterpolatingProvider {
+    provider: Box<dyn TrackProvider>,
+    scheduler: schedule::Scheduler,
+    last: Option<(Option<UnifiedTrack>, Instant)>,
+    next_poll: Instant,
+}
+impl InterpolatingProvider {
+    pub fn new(provider: Box<dyn TrackProvider>, scheduler: schedule::Scheduler) -> Self {
+        Self {
+            provider,
+            scheduler,
+            last: None,
+            next_poll: Instant::now(),
+        }
+    }
+
+    fn extrapolate(track: &UnifiedTrack, elapsed: Duration) -> UnifiedTrack {
+        let mut track = track.clone();
+        if !track.paused {
+            track.progress = track.progress.map(|progress| {
+                let progress = progress + elapsed;
+                track.duration.map_or(progress, |duration| progress.min(duration))
+            });
+        }
+        track
+    }
+}
+#[async_trait]
+impl TrackProvider for InterpolatingProvider {
+    async fn get_current_track(&mut self) -> anyhow::Result<Option<UnifiedTrack>> {
+        if let Some((track, fetched)) = &self.last {
+            let elapsed = fetched.elapsed();
+            let track = track.as_ref().map(|track| Self::extrapolate(track, elapsed));
+            // a finished track means that something else is probably playing now
+            let finished = track.as_ref().is_some_and(|track| {
+                !track.paused
+                    && track
+                        .progress
+                        .zip(track.duration)
+                        .is_some_and(|(progress, duration)| progress >= duration)
+            });
+            if Instant::now() < self.next_poll && !finished {
+                return Ok(track);
+            }
+        }
+
+        match self.provider.get_current_track().await {
+            Ok(track) => {
+                self.next_poll = Instant::now() + self.scheduler.after_poll(track.as_ref());
+                self.last = Some((track.clone(), Instant::now()));
+                Ok(track)
+            }
+            Err(e) => {
+                self.next_poll = Instant::now() + self.scheduler.after_error();
+                Err(e)
+            }
+        }
+    }
+}
+async fn create_provider(service: Service, proxy: Option<&ProxyConfig>) -> Box<dyn TrackProvider> {
+    let http_client = || proxy::http_client(proxy).expect("failed to create http client");
+
+    match service {
         Service::Spotify => {
             let mut client = spotify::Client::new(
+                http_client(),
                 env!("SPOTIFY_CLIENT_ID").to_string(),
                 env!("SPOTIFY_CLIENT_SECRET").to_string(),
                 spotify::FileTokenStorage::load_or_create(PathBuf::from("token.json"))
@@ -95,15 +472,104 @@ async fn main() {
 
         #[cfg(target_os = "macos")]
         Service::AppleMusic => Box::new(AppleMusicProvider::new()),
-    };
 
-    let updater: Box<dyn Updater> = match config.telegram {
+        #[cfg(target_os = "linux")]
+        Service::Mpris { player } => {
+            let client = mpris::Client::session(player)
+                .await
+                .expect("failed to connect to session bus");
+            Box::new(MprisProvider::new(client))
+        }
+
+        Service::Mpd { host, port, password } => Box::new(MpdProvider::new(mpd::Client::new(host, port, password))),
+
+        Service::LastFm {
+            api_key,
+            username,
+            api_url,
+        } => Box::new(LastFmProvider::new(lastfm::Client::new(
+            http_client(),
+            api_url,
+            api_key,
+            username,
+        ))),
+
+        Service::ListenBrainz {
+            token,
+            username,
+            api_root,
+        } => Box::new(ListenBrainzProvider::new(listenbrainz::Client::new(
+            http_client(),
+            api_root,
+            token,
+            username,
+        ))),
+
+        Service::Jellyfin { url, api_key, username } => Box::new(JellyfinProvider::new(jellyfin::Client::new(
+            http_client(),
+            jellyfin::Server::Jellyfin,
+            url,
+            api_key,
+            username,
+        ))),
+
+        Service::Emby { url, api_key, username } => Box::new(JellyfinProvider::new(jellyfin::Client::new(
+            http_client(),
+            jellyfin::Server::Emby,
+            url,
+            api_key,
+            username,
+        ))),
+
+        Service::Subsonic {
+            url,
+            username,
+            password,
+        } => Box::new(SubsonicProvider::new(subsonic::Client::new(
+            http_client(),
+            url,
+            username,
+            password,
+        ))),
+
+        Service::Plex { url, token, username } => Box::new(PlexProvider::new(plex::Client::new(
+            http_client(),
+            url,
+            token,
+            username,
+        ))),
+
+        Service::Vlc { url, password } => Box::new(VlcProvider::new(vlc::Client::new(http_client(), url, password))),
+
+        Service::Command { argv, timeout } => {
+            let client = command::Client::new(argv, Duration::from_secs(timeout)).expect("invalid command");
+            Box::new(CommandProvider::new(client))
+        }
+
+        Service::Push { address, token, expiry } => {
+            let client =
+                push::Client::new(&address, token, Duration::from_secs(expiry)).expect("failed to start push server");
+            Box::new(PushProvider::new(client))
+        }
+
+        Service::File { path, format } => {
+            let client = file::Client::new(path, &format).expect("failed to watch file");
+            Box::new(FileProvider::new(client))
+        }
+    }
+}
+
+async fn create_updater(telegram: TelegramConfig, proxy: Option<&ProxyConfig>) -> (String, Box<dyn Updater>) {
+    match telegram {
         TelegramConfig::Bio { api_id, api_hash, .. } => {
-            let client = telegram::create_client(api_id, api_hash)
+            let client = telegram::create_client(api_id, api_hash, proxy)
                 .await
                 .expect("failed to create telegram client");
             telegram::authorize(&client).await.expect("failed to authorize");
-            Box::new(telegram::BioUpdater(client))
+            let updater = telegram::BioUpdater::new(client)
+                .await
+                .expect("failed to get account info");
+            ("bio".to_string(), Box::new(updater))
         }
 
         TelegramConfig::Channel {
@@ -111,20 +577,188 @@ async fn main() {
             channel_id,
             message_id,
             ..
-        } => Box::new(telegram::ChannelUpdater {
-            token,
-            channel_id,
-            message_id,
-        }),
-    };
+        } => (
+            format!("channel {channel_id}"),
+            Box::new(telegram::ChannelUpdater {
+                client: proxy::http_client(proxy).expect("failed to create http client"),
+                token,
+                channel_id,
+                message_id,
+            }),
+        ),
+    }
+}
+/// Resolves on Ctrl+C or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+}
+
+/// How long a single update may take before it is given up on, longer than
+/// [`proxy::REQUEST_TIMEOUT`] to leave room for MTProto reconnects.
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(30);
+/// An updater with its own settings and the last text it has sent.
+struct Output<'a> {
+    name: String,
+    updater: Box<dyn Updater>,
+    template: &'a Template,
+    paused_template: Option<&'a Template>,
+    max_length: usize,
+    last_text: String,
+    limit: Option<rate_limit::TokenBucket>,
+    /// Set after a flood wait, no updates are sent until then.
+    suspended_until: Option<Instant>,
+}
+impl Output<'_> {
+    /// Sends `text` unless it hasn't changed or the output is held back by a
+    /// flood wait or its hourly limit.
+    async fn send(&mut self, text: String) {
+        if text == self.last_text {
+            log::info!("{}: text is the same as last time, skipping update", self.name);
+            return;
+        }
+
+        if self.suspended_until.is_some_and(|until| Instant::now() < until) {
+            log::info!("{}: waiting out the flood limit, skipping update", self.name);
+            return;
+        }
+        self.suspended_until = None;
+
+        if self.limit.as_mut().is_some_and(|limit| !limit.has_token()) {
+            log::info!("{}: hourly update limit reached, skipping update", self.name);
+            return;
+        }
+
+        let result = tokio::time::timeout(UPDATE_TIMEOUT, self.updater.update(text.clone()))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {UPDATE_TIMEOUT:?}")));
+        match result {
+            Ok(_) => {
+                self.last_text = text;
+                // only updates that went through count towards the limit
+                if let Some(limit) = &mut self.limit {
+                    limit.take();
+                }
+                log::info!("{}: updated successfully", self.name)
+            }
+            Err(e) => {
+                if !self.suspend_on_flood_wait(&e) {
+                    log::error!("{}: failed to update: {e}", self.name);
+                }
+            }
+        }
+    }
+
+    /// Suspends the output if `error` is a flood wait, returns whether it was one.
+    fn suspend_on_flood_wait(&mut self, error: &anyhow::Error) -> bool {
+        let Some(telegram::FloodWait(wait)) = error.downcast_ref() else {
+            return false;
+        };
+        log::warn!(
+            "{}: too many requests, pausing updates for {}s",
+            self.name,
+            wait.as_secs()
+        );
+        self.suspended_until = Some(Instant::now() + *wait);
+        true
+    }
+
+    /// Puts back the original text. Telegram refuses it during a flood wait,
+    /// so that is waited out first, and once more if the restore runs into one.
+    async fn restore(&mut self) -> anyhow::Result<()> {
+        let mut result = Ok(());
+        for _ in 0..2 {
+            if let Some(until) = self.suspended_until.take().filter(|until| *until > Instant::now()) {
+                log::info!(
+                    "{}: waiting {}s for the flood limit before restoring",
+                    self.name,
+                    until.saturating_duration_since(Instant::now()).as_secs()
+                );
+                tokio::time::sleep_until(until.into()).await;
+            }
+
+            result = self.updater.restore().await;
+            match &result {
+                Err(e) if self.suspend_on_flood_wait(e) => continue,
+                _ => break,
+            }
+        }
+        result
+    }
+}
+#[tokio::main]
+async fn main() {
+    TermLogger::init(
+        LevelFilter::Info,
+        simplelog::ConfigBuilder::new()
+            .add_filter_allow_str(module_path!())
+            .build(),
+        simplelog::TerminalMode::Mixed,
+        simplelog::ColorChoice::Auto,
+    )
+    .unwrap();
+
+    let config = Config::load_or_create(PathBuf::from("config.json")).expect("failed to load config");
+    let mut providers = Vec::new();
+    for service in config.service {
+        let name = service.name();
+        // other sources are often on the local network, so only spotify falls back to the global proxy
+        let proxy = match service {
+            Service::Spotify => config.proxies.get(name).or(config.proxy.as_ref()),
+            _ => config.proxies.get(name),
+        };
+        providers.push((name, create_provider(service, proxy).await));
+    }
+    let poll_interval = Duration::from_secs(config.poll_interval.unwrap_or(config.interval));
+    let mut track_provider: Box<dyn TrackProvider> = Box::new(InterpolatingProvider::new(
+        Box::new(FallbackProvider::new(providers)),
+        schedule::Scheduler::new(poll_interval, config.schedule.as_ref()),
+    ));
+
+    let mut outputs = Vec::new();
+    for output in &config.telegram {
+        let proxy = output.proxy.as_ref().or(config.proxy.as_ref());
+        let (name, updater) = create_updater(output.telegram.clone(), proxy).await;
+        let max_length = output.max_length.or(config.max_length).unwrap_or(updater.max_length());
+        log::info!("text for {name} is limited to {max_length} characters");
+
+        outputs.push(Output {
+            name,
+            updater,
+            template: output.template.as_ref().unwrap_or(&config.template),
+            paused_template: output.paused_template.as_ref().or(config.paused_template.as_ref()),
+            max_length,
+            last_text: String::new(),
+            limit: output
+                .max_updates_per_hour
+                .or(config.max_updates_per_hour)
+                .map(rate_limit::TokenBucket::per_hour),
+            suspended_until: None,
+        });
+    }
 
     let mut interval = tokio::time::interval(Duration::from_secs(config.interval));
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
-    let mut last_text = String::new();
+    let mut paused_since: Option<Instant> = None;
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
 
     loop {
-        interval.tick().await;
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = &mut shutdown => break,
+        }
 
         let track = match track_provider.get_current_track().await {
             Ok(track) => track,
@@ -134,39 +768,57 @@ async fn main() {
             }
         };
 
-        let text = match track {
+        // the track to render, `None` when `default` should be shown
+        let track = match track {
             Some(track) => {
                 log::info!("current track: {track:?}");
 
-                fn format_duration(duration: Duration) -> String {
-                    let total_seconds = duration.as_secs();
-                    let minutes = total_seconds / 60;
-                    let seconds = total_seconds % 60;
-                    format!("{minutes}:{seconds:02}")
-                }
+                let paused_for = if track.paused {
+                    paused_since.get_or_insert_with(Instant::now).elapsed()
+                } else {
+                    paused_since = None;
+                    Duration::ZERO
+                };
 
-                config
-                    .template
-                    .replace("{artist}", &track.artist)
-                    .replace("{title}", &track.title)
-                    .replace("{progress}", &format_duration(track.progress))
-                    .replace("{duration}", &format_duration(track.duration))
+                match config.paused_timeout {
+                    Some(timeout) if track.paused && paused_for >= Duration::from_secs(timeout) => None,
+                    _ => Some(track),
+                }
             }
 
-            None => config.default.clone(),
+            None => {
+                paused_since = None;
+                None
+            }
         };
 
-        if text == last_text {
-            log::info!("text is the same as last time, skipping update");
-            continue;
-        }
+        let updates = outputs.iter_mut().map(|output| {
+            let context = template::RenderContext {
+                bar: &config.bar,
+                original: output.updater.original(),
+            };
+            let text = match &track {
+                Some(track) => match output.paused_template.filter(|_| track.paused) {
+                    Some(paused_template) => paused_template.render_within(track, &context, output.max_length),
+                    None => output.template.render_within(track, &context, output.max_length),
+                },
+                None => template::truncate(&config.default, output.max_length),
+            };
+            output.send(text)
+        });
+        // outputs are updated side by side, so a slow one doesn't hold up the rest
+        futures_util::future::join_all(updates).await;
+    }
 
-        match updater.update(text.clone()).await {
-            Ok(_) => {
-                last_text = text;
-                log::info!("updated successfully")
-            }
-            Err(e) => log::error!("failed to update: {e}"),
+    log::info!("shutting down, stop again to skip restoring");
+    let restores = outputs.iter_mut().map(|output| async move {
+        match output.restore().await {
+            Ok(_) => log::info!("{}: restored successfully", output.name),
+            Err(e) => log::error!("{}: failed to restore: {e}", output.name),
         }
+    });
+    tokio::select! {
+        _ = futures_util::future::join_all(restores) => {}
+        _ = shutdown_signal() => log::warn!("stopped without restoring"),
     }
 }
@@ -0,0 +1,426 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time::timeout,
+};
+
+use crate::UnifiedTrack;
+
+#[derive(Debug)]
+pub struct Track {
+    pub state: State,
+    pub artists: Vec<String>,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artists.join(", "),
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            paused: val.state != State::Play,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum State {
+    Play,
+    Pause,
+    Stop,
+}
+
+impl From<&str> for State {
+    fn from(s: &str) -> Self {
+        match s {
+            "play" => Self::Play,
+            "pause" => Self::Pause,
+            _ => Self::Stop,
+        }
+    }
+}
+
+/// How long connecting or a single command may take before the server is considered unresponsive.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    async fn connect(address: &str, password: Option<&str>) -> anyhow::Result<Self> {
+        timeout(TIMEOUT, Self::handshake(address, password))
+            .await
+            .context("timed out connecting to mpd")?
+    }
+
+    async fn handshake(address: &str, password: Option<&str>) -> anyhow::Result<Self> {
+        let stream = TcpStream::connect(address).await.context("error connecting to mpd")?;
+        let mut connection = Self {
+            stream: BufReader::new(stream),
+        };
+
+        let mut greeting = String::new();
+        connection.stream.read_line(&mut greeting).await?;
+        if !greeting.starts_with("OK MPD ") {
+            anyhow::bail!("unexpected mpd greeting: {}", greeting.trim_end());
+        }
+
+        if let Some(password) = password {
+            connection
+                .command(&format!("password {}", quote(password)))
+                .await
+                .context("error authenticating")?;
+        }
+
+        Ok(connection)
+    }
+
+    async fn command(&mut self, command: &str) -> anyhow::Result<Vec<(String, String)>> {
+        timeout(TIMEOUT, self.exchange(command))
+            .await
+            // only the command name, `password` carries the password
+            .with_context(|| {
+                let name = command.split(' ').next().unwrap_or_default();
+                format!("timed out waiting for mpd to answer `{name}`")
+            })?
+    }
+
+    async fn exchange(&mut self, command: &str) -> anyhow::Result<Vec<(String, String)>> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{command}\n").as_bytes())
+            .await?;
+
+        let mut pairs = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                anyhow::bail!("connection closed by mpd");
+            }
+
+            let line = line.trim_end_matches('\n');
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                anyhow::bail!("mpd error: {error}");
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn parse_seconds(s: &str) -> Option<Duration> {
+    s.parse::<f64>().ok().filter(|s| *s >= 0.0).map(Duration::from_secs_f64)
+}
+
+pub struct Client {
+    address: String,
+    password: Option<String>,
+    connection: Option<Connection>,
+}
+
+impl Client {
+    pub fn new(host: String, port: u16, password: Option<String>) -> Self {
+        Self {
+            address: format!("{host}:{port}"),
+            password,
+            connection: None,
+        }
+    }
+
+    async fn query(&mut self) -> anyhow::Result<Option<Track>> {
+        // the connection is only put back after a full exchange, so a query that
+        // fails or is cancelled halfway never leaves unread lines behind
+        let mut connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => Connection::connect(&self.address, self.password.as_deref()).await?,
+        };
+
+        let status = connection.command("status").await?;
+        let song = connection.command("currentsong").await?;
+        self.connection = Some(connection);
+
+        let mut state = State::Stop;
+        let mut progress = None;
+        let mut duration = None;
+        for (key, value) in &status {
+            match key.as_str() {
+                "state" => state = value.as_str().into(),
+                "elapsed" => progress = parse_seconds(value),
+                "duration" => duration = parse_seconds(value),
+                // older servers only report "time: <elapsed>:<total>" in whole seconds
+                "time" => {
+                    if let Some((elapsed, total)) = value.split_once(':') {
+                        progress = progress.or(parse_seconds(elapsed));
+                        duration = duration.or(parse_seconds(total));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if state == State::Stop || song.is_empty() {
+            return Ok(None);
+        }
+
+        let mut artists = Vec::new();
+        let mut title = None;
+        let mut file = None;
+        for (key, value) in song {
+            match key.as_str() {
+                "Artist" => artists.push(value),
+                "Title" => title = Some(value),
+                "file" => file = Some(value),
+                "duration" => duration = duration.or(parse_seconds(&value)),
+                _ => {}
+            }
+        }
+
+        let title = title
+            .or_else(|| file.map(|file| file.rsplit('/').next().unwrap_or_default().to_string()))
+            .unwrap_or_default();
+
+        Ok(Some(Track {
+            state,
+            artists,
+            title,
+            progress: progress.unwrap_or_default(),
+            duration,
+        }))
+    }
+
+    pub async fn get_current_track(&mut self) -> anyhow::Result<Option<Track>> {
+        let reused = self.connection.is_some();
+        match self.query().await {
+            Ok(track) => Ok(track),
+            Err(e) => {
+                if !reused {
+                    return Err(e);
+                }
+
+                log::warn!("mpd connection lost, reconnecting: {e}");
+                self.query().await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    struct FakeMpd {
+        greeting: &'static str,
+        /// The expected argument of `password`, as it is sent over the wire.
+        password: Option<&'static str>,
+        status: &'static str,
+        song: &'static str,
+        /// Drops the connection after answering this many commands.
+        drop_after: Option<usize>,
+    }
+
+    impl Default for FakeMpd {
+        fn default() -> Self {
+            Self {
+                greeting: "OK MPD 0.23.5",
+                password: None,
+                status: "",
+                song: "",
+                drop_after: None,
+            }
+        }
+    }
+
+    impl FakeMpd {
+        /// Starts serving on a random local port, returns the port and the number of accepted connections.
+        async fn spawn(self) -> (u16, Arc<AtomicUsize>) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let connections = Arc::new(AtomicUsize::new(0));
+            let fake = Arc::new(self);
+
+            let accepted = connections.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(fake.clone().handle(stream));
+                }
+            });
+
+            (port, connections)
+        }
+
+        async fn handle(self: Arc<Self>, stream: TcpStream) {
+            let mut stream = BufReader::new(stream);
+            let greeting = format!("{}\n", self.greeting);
+            stream.get_mut().write_all(greeting.as_bytes()).await.unwrap();
+
+            let mut answered = 0;
+            let mut line = String::new();
+            while stream.read_line(&mut line).await.unwrap_or(0) > 0 {
+                if self.drop_after.is_some_and(|drop_after| answered >= drop_after) {
+                    return;
+                }
+
+                let response = match line.trim_end() {
+                    "status" => format!("{}OK\n", self.status),
+                    "currentsong" => format!("{}OK\n", self.song),
+                    command => match (command.strip_prefix("password "), self.password) {
+                        (Some(given), Some(password)) if given == password => "OK\n".to_string(),
+                        (Some(_), _) => "ACK [3@0] {password} incorrect password\n".to_string(),
+                        _ => format!("ACK [5@0] {{}} unknown command \"{command}\"\n"),
+                    },
+                };
+                stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                answered += 1;
+                line.clear();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_status_and_current_song() {
+        let (port, _) = FakeMpd {
+            status: "volume: 50\nstate: play\nelapsed: 12.500\nduration: 200.250\n",
+            song: "file: music/a.flac\nArtist: First\nArtist: Second\nTitle: Song\n",
+            ..Default::default()
+        }
+        .spawn()
+        .await;
+
+        let track = Client::new("127.0.0.1".to_string(), port, None)
+            .get_current_track()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(track.state, State::Play);
+        assert_eq!(track.artists, ["First", "Second"]);
+        assert_eq!(track.title, "Song");
+        assert_eq!(track.progress, Duration::from_millis(12_500));
+        assert_eq!(track.duration, Some(Duration::from_millis(200_250)));
+    }
+
+    #[tokio::test]
+    async fn reads_legacy_time_and_falls_back_to_file_name() {
+        let (port, _) = FakeMpd {
+            status: "state: pause\ntime: 30:240\n",
+            song: "file: music/album/Untitled.mp3\n",
+            ..Default::default()
+        }
+        .spawn()
+        .await;
+
+        let track = Client::new("127.0.0.1".to_string(), port, None)
+            .get_current_track()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(track.state, State::Pause);
+        assert!(track.artists.is_empty());
+        assert_eq!(track.title, "Untitled.mp3");
+        assert_eq!(track.progress, Duration::from_secs(30));
+        assert_eq!(track.duration, Some(Duration::from_secs(240)));
+    }
+
+    #[tokio::test]
+    async fn returns_nothing_when_stopped() {
+        let (port, _) = FakeMpd {
+            status: "state: stop\n",
+            ..Default::default()
+        }
+        .spawn()
+        .await;
+
+        let track = Client::new("127.0.0.1".to_string(), port, None)
+            .get_current_track()
+            .await
+            .unwrap();
+        assert!(track.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_unexpected_greeting() {
+        let (port, _) = FakeMpd {
+            greeting: "SSH-2.0-OpenSSH_9.6",
+            ..Default::default()
+        }
+        .spawn()
+        .await;
+
+        let error = Client::new("127.0.0.1".to_string(), port, None)
+            .get_current_track()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("unexpected mpd greeting"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn sends_password() {
+        let fake = || FakeMpd {
+            password: Some(r#""se\"cret""#),
+            status: "state: play\n",
+            song: "Title: Song\n",
+            ..Default::default()
+        };
+
+        let (port, _) = fake().spawn().await;
+        let track = Client::new("127.0.0.1".to_string(), port, Some("se\"cret".to_string()))
+            .get_current_track()
+            .await
+            .unwrap();
+        assert_eq!(track.unwrap().title, "Song");
+
+        let (port, _) = fake().spawn().await;
+        let error = Client::new("127.0.0.1".to_string(), port, Some("wrong".to_string()))
+            .get_current_track()
+            .await
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("incorrect password"), "{error:#}");
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_connection_drops() {
+        let (port, connections) = FakeMpd {
+            status: "state: play\n",
+            song: "Title: Song\n",
+            drop_after: Some(2),
+            ..Default::default()
+        }
+        .spawn()
+        .await;
+
+        let mut client = Client::new("127.0.0.1".to_string(), port, None);
+        for _ in 0..3 {
+            let track = client.get_current_track().await.unwrap();
+            assert_eq!(track.unwrap().title, "Song");
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use zbus::{
+    fdo::DBusProxy,
+    proxy,
+    proxy::CacheProperties,
+    zvariant::{OwnedValue, Value},
+    Connection,
+};
+
+use crate::{PlayingFirst, UnifiedTrack};
+
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+}
+
+#[derive(Debug)]
+pub struct Track {
+    pub player: String,
+    pub status: PlaybackStatus,
+    pub artists: Vec<String>,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artists.join(", "),
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            paused: val.status != PlaybackStatus::Playing,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+impl From<&str> for PlaybackStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "Playing" => Self::Playing,
+            "Paused" => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+}
+
+pub struct Client {
+    connection: Connection,
+    player: Option<String>,
+}
+
+impl Client {
+    /// Looks for players on the bus behind `connection`, normally the session
+    /// bus. `player` limits the lookup to bus names `org.mpris.MediaPlayer2.<player>`
+    /// (and its `.instanceN` variants).
+    pub fn new(connection: Connection, player: Option<String>) -> Self {
+        Self { connection, player }
+    }
+
+    pub async fn session(player: Option<String>) -> anyhow::Result<Self> {
+        let connection = Connection::session().await.context("error connecting to session bus")?;
+        Ok(Self::new(connection, player))
+    }
+
+    async fn player_names(&self) -> anyhow::Result<Vec<String>> {
+        let names = DBusProxy::new(&self.connection).await?.list_names().await?;
+        let mut names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| match name.strip_prefix(BUS_NAME_PREFIX) {
+                Some(player) => match &self.player {
+                    Some(filter) => player == filter || player.starts_with(&format!("{filter}.")),
+                    None => true,
+                },
+                None => false,
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn get_player_track(&self, name: String) -> anyhow::Result<Track> {
+        let proxy = PlayerProxy::builder(&self.connection)
+            .destination(name.clone())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        let status = proxy.playback_status().await?.as_str().into();
+        let metadata = proxy.metadata().await?;
+        // not every player implements Position, treat it as the beginning of the track
+        let position = proxy.position().await.unwrap_or_default();
+
+        let artists = metadata
+            .get("xesam:artist")
+            .and_then(|value| Vec::<String>::try_from(value.try_clone().ok()?).ok())
+            .unwrap_or_default();
+        let title = metadata
+            .get("xesam:title")
+            .and_then(|value| String::try_from(value.try_clone().ok()?).ok())
+            .unwrap_or_default();
+        let length = match metadata.get("mpris:length").map(|value| &**value) {
+            Some(Value::I64(length)) => Some(*length),
+            Some(Value::U64(length)) => Some(*length as i64),
+            _ => None,
+        };
+
+        Ok(Track {
+            player: name,
+            status,
+            artists,
+            title,
+            progress: Duration::from_micros(position.max(0) as u64),
+            duration: length.map(|length| Duration::from_micros(length.max(0) as u64)),
+        })
+    }
+
+    /// Returns the track of one of the players, picked with [`PlayingFirst`].
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        let mut found = PlayingFirst::default();
+
+        for name in self.player_names().await? {
+            let track = match self.get_player_track(name.clone()).await {
+                Ok(track) => track,
+                Err(e) => {
+                    log::warn!("error reading mpris player {name}: {e}");
+                    continue;
+                }
+            };
+
+            if track.status == PlaybackStatus::Stopped || track.title.is_empty() {
+                continue;
+            }
+
+            let paused = track.status != PlaybackStatus::Playing;
+            if found.offer(track, paused) {
+                break;
+            }
+        }
+
+        Ok(found.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    use zbus::{connection, interface, zvariant::Array};
+
+    use super::*;
+
+    /// A private bus that is shut down with the test.
+    struct Bus(Child);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Starts a private `dbus-daemon` and returns it with its address.
+    fn start_bus() -> (Bus, String) {
+        let child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("the mpris tests need dbus-daemon installed");
+        let mut bus = Bus(child);
+
+        let mut address = String::new();
+        BufReader::new(bus.0.stdout.as_mut().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        (bus, address.trim().to_string())
+    }
+
+    struct FakePlayer {
+        status: &'static str,
+        artists: Vec<&'static str>,
+        title: &'static str,
+        length: i64,
+        position: i64,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.to_string()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            HashMap::from([
+                (
+                    "xesam:artist".to_string(),
+                    Value::from(Array::from(self.artists.clone())).try_into().unwrap(),
+                ),
+                ("xesam:title".to_string(), Value::from(self.title).try_into().unwrap()),
+                ("mpris:length".to_string(), Value::from(self.length).try_into().unwrap()),
+            ])
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            self.position
+        }
+    }
+
+    async fn serve(address: &str, name: &str, player: FakePlayer) -> Connection {
+        connection::Builder::address(address)
+            .unwrap()
+            .name(format!("{BUS_NAME_PREFIX}{name}"))
+            .unwrap()
+            .serve_at("/org/mpris/MediaPlayer2", player)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    async fn client(address: &str, player: Option<&str>) -> Client {
+        let connection = connection::Builder::address(address).unwrap().build().await.unwrap();
+        Client::new(connection, player.map(str::to_string))
+    }
+
+    #[tokio::test]
+    async fn reads_players_from_bus() {
+        let (_bus, address) = start_bus();
+
+        let _paused = serve(
+            &address,
+            "paused",
+            FakePlayer {
+                status: "Paused",
+                artists: vec!["Paused Artist"],
+                title: "Paused Title",
+                length: 200_000_000,
+                position: 10_000_000,
+            },
+        )
+        .await;
+
+        assert_eq!(
+            client(&address, Some("playing"))
+                .await
+                .get_current_track()
+                .await
+                .unwrap()
+                .map(|track| track.title),
+            None
+        );
+
+        let paused = client(&address, None).await.get_current_track().await.unwrap().unwrap();
+        assert_eq!(paused.player, "org.mpris.MediaPlayer2.paused");
+        assert_eq!(paused.status, PlaybackStatus::Paused);
+
+        let _playing = serve(
+            &address,
+            "playing.instance42",
+            FakePlayer {
+                status: "Playing",
+                artists: vec!["First", "Second"],
+                title: "Song",
+                length: 180_000_000,
+                position: 42_500_000,
+            },
+        )
+        .await;
+
+        let track = client(&address, None).await.get_current_track().await.unwrap().unwrap();
+        assert_eq!(track.player, "org.mpris.MediaPlayer2.playing.instance42");
+        assert_eq!(track.status, PlaybackStatus::Playing);
+        assert_eq!(track.artists, ["First", "Second"]);
+        assert_eq!(track.title, "Song");
+        assert_eq!(track.progress, Duration::from_millis(42_500));
+        assert_eq!(track.duration, Some(Duration::from_secs(180)));
+
+        let filtered = client(&address, Some("paused"))
+            .await
+            .get_current_track()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(filtered.title, "Paused Title");
+    }
+
+    #[tokio::test]
+    async fn skips_stopped_players() {
+        let (_bus, address) = start_bus();
+
+        let _stopped = serve(
+            &address,
+            "stopped",
+            FakePlayer {
+                status: "Stopped",
+                artists: vec!["Artist"],
+                title: "Title",
+                length: 100_000_000,
+                position: 0,
+            },
+        )
+        .await;
+
+        assert!(client(&address, None)
+            .await
+            .get_current_track()
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{PlayingFirst, UnifiedTrack};
+
+#[derive(Debug)]
+pub struct Track {
+    pub paused: bool,
+    pub artist: String,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            paused: val.paused,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+    username: String,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, url: String, token: String, username: String) -> Self {
+        Self {
+            client,
+            url,
+            token,
+            username,
+        }
+    }
+
+    /// Returns the track of one of the account's sessions, picked with [`PlayingFirst`].
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(rename = "MediaContainer")]
+            media_container: MediaContainer,
+        }
+
+        #[derive(Deserialize)]
+        struct MediaContainer {
+            #[serde(rename = "Metadata", default)]
+            metadata: Vec<Metadata>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Metadata {
+            r#type: String,
+            #[serde(default)]
+            title: String,
+            // the album artist, used when the track has no artist of its own
+            #[serde(default)]
+            grandparent_title: String,
+            original_title: Option<String>,
+            #[serde(default)]
+            view_offset: u64,
+            duration: Option<u64>,
+            #[serde(rename = "User")]
+            user: Option<User>,
+            #[serde(rename = "Player")]
+            player: Option<Player>,
+        }
+
+        #[derive(Deserialize)]
+        struct User {
+            title: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Player {
+            state: String,
+        }
+
+        let response: Response = self
+            .client
+            .get(format!("{}/status/sessions", self.url.trim_end_matches('/')))
+            .header("X-Plex-Token", &self.token)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .context("error doing request")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("error parsing sessions")?;
+
+        let mut found = PlayingFirst::default();
+        for metadata in response.media_container.metadata {
+            if metadata.r#type != "track" {
+                continue;
+            }
+            if !metadata
+                .user
+                .is_some_and(|user| user.title.eq_ignore_ascii_case(&self.username))
+            {
+                continue;
+            }
+
+            let track = Track {
+                paused: metadata.player.is_some_and(|player| player.state != "playing"),
+                artist: metadata.original_title.unwrap_or(metadata.grandparent_title),
+                title: metadata.title,
+                progress: Duration::from_millis(metadata.view_offset),
+                duration: metadata.duration.map(Duration::from_millis),
+            };
+
+            let paused = track.paused;
+            if found.offer(track, paused) {
+                break;
+            }
+        }
+
+        Ok(found.into_inner())
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use tiny_http::{Method, Request, Response, Server};
+
+use crate::UnifiedTrack;
+
+pub const DEFAULT_ADDRESS: &str = "0.0.0.0:3001";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Track {
+    #[serde(default)]
+    pub artist: String,
+    pub title: String,
+    pub progress_ms: Option<u64>,
+    pub duration_ms: Option<u64>,
+    #[serde(default = "default_playing")]
+    pub playing: bool,
+}
+
+fn default_playing() -> bool {
+    true
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: val.progress_ms.map(Duration::from_millis),
+            duration: val.duration_ms.map(Duration::from_millis),
+            paused: !val.playing,
+            ..Default::default()
+        }
+    }
+}
+
+type Slot = Arc<Mutex<Option<(Track, Instant)>>>;
+
+fn respond(request: Request, status: u16, body: &str) {
+    request
+        .respond(Response::from_string(body).with_status_code(status))
+        .ok();
+}
+
+fn is_authorized(request: &Request, token: &str) -> bool {
+    let header = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str());
+    if header.and_then(|value| value.strip_prefix("Bearer ")) == Some(token) {
+        return true;
+    }
+
+    // Shortcuts and Tasker make it easier to pass the token in the url
+    let query = request
+        .url()
+        .split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or_default();
+    form_urlencoded::parse(query.as_bytes()).any(|(key, value)| key == "token" && value == token)
+}
+
+fn serve(server: Server, token: String, slot: Slot) {
+    for mut request in server.incoming_requests() {
+        if !is_authorized(&request, &token) {
+            respond(request, 401, "unauthorized");
+            continue;
+        }
+
+        match request.method() {
+            Method::Post | Method::Put => {
+                let mut body = String::new();
+                if let Err(e) = request.as_reader().read_to_string(&mut body) {
+                    respond(request, 400, &format!("error reading body: {e}"));
+                    continue;
+                }
+
+                // an empty body means that nothing is playing anymore
+                if body.trim().is_empty() {
+                    *slot.lock().unwrap() = None;
+                    respond(request, 200, "ok");
+                    continue;
+                }
+
+                match serde_json::from_str::<Track>(&body) {
+                    Ok(track) => {
+                        log::info!("pushed track: {track:?}");
+                        *slot.lock().unwrap() = Some((track, Instant::now()));
+                        respond(request, 200, "ok");
+                    }
+                    Err(e) => respond(request, 400, &format!("error parsing track: {e}")),
+                }
+            }
+
+            Method::Delete => {
+                *slot.lock().unwrap() = None;
+                respond(request, 200, "ok");
+            }
+
+            _ => respond(request, 405, "method not allowed"),
+        }
+    }
+}
+
+pub struct Client {
+    slot: Slot,
+    expiry: Duration,
+}
+
+impl Client {
+    /// Starts listening on `address` in a background thread. Requests must carry
+    /// `token` either as a bearer token or as the `token` query parameter.
+    pub fn new(address: &str, token: String, expiry: Duration) -> anyhow::Result<Self> {
+        // an empty token would match an empty `?token=` or `Bearer ` and let anyone in
+        if token.trim().is_empty() {
+            anyhow::bail!("push token must not be empty");
+        }
+
+        let server = Server::http(address).map_err(|e| anyhow::anyhow!("error listening on {address}: {e}"))?;
+        let slot = Slot::default();
+
+        let server_slot = slot.clone();
+        std::thread::spawn(move || serve(server, token, server_slot));
+        log::info!("listening for pushed tracks on {address}");
+
+        Ok(Self { slot, expiry })
+    }
+
+    /// Returns the last pushed track unless it is older than the expiry.
+    pub fn get_current_track(&self) -> Option<Track> {
+        let mut slot = self.slot.lock().unwrap();
+        if slot.as_ref().is_some_and(|(_, pushed)| pushed.elapsed() > self.expiry) {
+            *slot = None;
+        }
+        slot.as_ref().map(|(track, _)| track.clone())
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Allows bursts of up to `capacity` updates and refills them evenly over an
+/// hour, so no more than `capacity` updates go out in any hour on average.
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_hour(capacity: u32) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Whether a token is available, without taking it.
+    pub fn has_token(&mut self) -> bool {
+        self.refill();
+        self.tokens >= 1.0
+    }
+
+    /// Takes a token, called after an update went through.
+    pub fn take(&mut self) {
+        self.refill();
+        self.tokens = (self.tokens - 1.0).max(0.0);
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let refill = (now - self.refilled).as_secs_f64() / HOUR.as_secs_f64() * self.capacity;
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.refilled = now;
+    }
+}
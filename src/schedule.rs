@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::UnifiedTrack;
+
+/// How long after the predicted end of a track to poll, so the next one has started.
+const END_MARGIN: Duration = Duration::from_secs(2);
+
+fn default_min_interval() -> u64 {
+    5
+}
+
+fn default_max_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    #[serde(default = "default_min_interval")]
+    pub min_interval: u64,
+    #[serde(default = "default_max_interval")]
+    pub max_interval: u64,
+}
+
+/// Decides when to poll the source next. Without a [`ScheduleConfig`] it polls
+/// every `interval`; with one it polls right after the current track should
+/// end, falls back to `interval` when that can't be known and backs off
+/// exponentially while nothing is playing.
+pub struct Scheduler {
+    interval: Duration,
+    bounds: Option<(Duration, Duration)>,
+    idle: Duration,
+}
+
+impl Scheduler {
+    pub fn new(interval: Duration, config: Option<&ScheduleConfig>) -> Self {
+        Self {
+            interval,
+            bounds: config.map(|config| {
+                let min = Duration::from_secs(config.min_interval);
+                (min, Duration::from_secs(config.max_interval).max(min))
+            }),
+            idle: Duration::ZERO,
+        }
+    }
+
+    pub fn after_error(&mut self) -> Duration {
+        self.clamp(self.interval)
+    }
+
+    pub fn after_poll(&mut self, track: Option<&UnifiedTrack>) -> Duration {
+        let Some((_, max)) = self.bounds else {
+            return self.interval;
+        };
+
+        let Some(track) = track else {
+            self.idle = if self.idle.is_zero() {
+                self.interval
+            } else {
+                (self.idle * 2).min(max)
+            };
+            return self.clamp(self.idle);
+        };
+        self.idle = Duration::ZERO;
+
+        let remaining = match (track.paused, track.progress, track.duration) {
+            (false, Some(progress), Some(duration)) if !duration.is_zero() => duration.saturating_sub(progress),
+            _ => return self.clamp(self.interval),
+        };
+        self.clamp(remaining + END_MARGIN)
+    }
+
+    fn clamp(&self, delay: Duration) -> Duration {
+        match self.bounds {
+            Some((min, max)) => delay.clamp(min, max),
+            None => delay,
+        }
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::Context;
 use reqwest::{StatusCode, Url};
 use serde::Deserialize;
 
-use crate::UnifiedTrack;
+use crate::{TrackKind, UnifiedTrack};
 
 use super::{Token, TokenStorage};
 
@@ -17,8 +17,16 @@ pub struct Client<T: TokenStorage> {
 
 #[derive(Debug)]
 pub struct Track {
+    pub kind: TrackKind,
+    pub id: Option<String>,
     pub artists: Vec<String>,
     pub title: String,
+    pub album: Option<String>,
+    pub album_artists: Vec<String>,
+    pub year: Option<u16>,
+    pub url: Option<String>,
+    pub cover_url: Option<String>,
+    pub explicit: bool,
     pub is_playing: bool,
     pub progress: Duration,
     pub duration: Duration,
@@ -29,8 +37,18 @@ impl From<Track> for UnifiedTrack {
         UnifiedTrack {
             artist: val.artists.join(", "),
             title: val.title,
-            progress: val.progress,
-            duration: val.duration,
+            progress: Some(val.progress),
+            duration: Some(val.duration),
+            kind: val.kind,
+            album: val.album,
+            album_artist: (!val.album_artists.is_empty()).then(|| val.album_artists.join(", ")),
+            year: val.year,
+            url: val.url,
+            cover_url: val.cover_url,
+            id: val.id,
+            explicit: val.explicit,
+            paused: !val.is_playing,
+            ..Default::default()
         }
     }
 }
@@ -57,9 +75,9 @@ impl<T: TokenStorage> Client<T> {
     const REDIRECT_URI: &str = "http://localhost:3000";
     const SCOPE: &str = "user-read-currently-playing";
 
-    pub fn new(client_id: String, client_secret: String, token_storage: T) -> Self {
+    pub fn new(client: reqwest::Client, client_id: String, client_secret: String, token_storage: T) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
             client_id,
             client_secret,
             token_storage,
@@ -150,26 +168,81 @@ impl<T: TokenStorage> Client<T> {
 
         #[derive(Deserialize)]
         struct Response {
-            item: Item,
+            // null while an ad is playing
+            item: Option<Item>,
             progress_ms: u64,
             is_playing: bool,
         }
 
         #[derive(Deserialize)]
-        struct Item {
+        #[serde(tag = "type", rename_all = "lowercase")]
+        enum Item {
+            Track {
+                // null for local files
+                id: Option<String>,
+                name: String,
+                artists: Vec<Artist>,
+                album: Album,
+                duration_ms: u64,
+                #[serde(default)]
+                explicit: bool,
+                #[serde(default)]
+                external_urls: ExternalUrls,
+            },
+            Episode {
+                id: String,
+                name: String,
+                show: Show,
+                duration_ms: u64,
+                #[serde(default)]
+                explicit: bool,
+                #[serde(default)]
+                external_urls: ExternalUrls,
+                #[serde(default)]
+                images: Vec<Image>,
+                release_date: Option<String>,
+            },
+        }
+
+        #[derive(Deserialize)]
+        struct Artist {
             name: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Album {
+            name: String,
+            #[serde(default)]
             artists: Vec<Artist>,
-            duration_ms: u64,
+            #[serde(default)]
+            images: Vec<Image>,
+            release_date: Option<String>,
         }
 
         #[derive(Deserialize)]
-        struct Artist {
+        struct Show {
             name: String,
         }
 
+        #[derive(Deserialize)]
+        struct Image {
+            url: String,
+        }
+
+        #[derive(Deserialize, Default)]
+        struct ExternalUrls {
+            spotify: Option<String>,
+        }
+
+        // release dates are "YYYY", "YYYY-MM" or "YYYY-MM-DD" depending on the precision
+        fn parse_year(release_date: Option<String>) -> Option<u16> {
+            release_date?.split('-').next()?.parse().ok()
+        }
+
         let response = self
             .client
             .get(CURRENT_TRACK_URL)
+            .query(&[("additional_types", "track,episode")])
             .header("Authorization", format!("Bearer {}", self.get_alive_token().await?))
             .send()
             .await?;
@@ -179,12 +252,64 @@ impl<T: TokenStorage> Client<T> {
         }
 
         let response: Response = response.json().await?;
-        Ok(Some(Track {
-            artists: response.item.artists.iter().map(|artist| artist.name.clone()).collect(),
-            title: response.item.name,
-            is_playing: response.is_playing,
-            duration: Duration::from_millis(response.item.duration_ms),
-            progress: Duration::from_millis(response.progress_ms),
+        let Some(item) = response.item else {
+            return Ok(None);
+        };
+
+        let progress = Duration::from_millis(response.progress_ms);
+        let is_playing = response.is_playing;
+
+        // images are sorted from the largest to the smallest
+        Ok(Some(match item {
+            Item::Track {
+                id,
+                name,
+                artists,
+                album,
+                duration_ms,
+                explicit,
+                external_urls,
+            } => Track {
+                kind: TrackKind::Track,
+                id,
+                artists: artists.into_iter().map(|artist| artist.name).collect(),
+                title: name,
+                album: Some(album.name),
+                album_artists: album.artists.into_iter().map(|artist| artist.name).collect(),
+                year: parse_year(album.release_date),
+                url: external_urls.spotify,
+                cover_url: album.images.into_iter().next().map(|image| image.url),
+                explicit,
+                is_playing,
+                progress,
+                duration: Duration::from_millis(duration_ms),
+            },
+
+            // the show stands in for the artist
+            Item::Episode {
+                id,
+                name,
+                show,
+                duration_ms,
+                explicit,
+                external_urls,
+                images,
+                release_date,
+            } => Track {
+                kind: TrackKind::Episode,
+                id: Some(id),
+                artists: vec![show.name],
+                title: name,
+                album: None,
+                album_artists: Vec::new(),
+                year: parse_year(release_date),
+                url: external_urls.spotify,
+                cover_url: images.into_iter().next().map(|image| image.url),
+                explicit,
+                is_playing,
+                progress,
+                duration: Duration::from_millis(duration_ms),
+            },
         }))
     }
 }
@@ -0,0 +1,323 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+
+use crate::UnifiedTrack;
+
+const API_VERSION: &str = "1.16.1";
+const CLIENT_NAME: &str = "playing-tg-bio";
+
+#[derive(Debug)]
+pub struct Track {
+    pub artist: String,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            ..Default::default()
+        }
+    }
+}
+
+// The same structs are used for the JSON and the XML flavours of the API,
+// the `@` aliases match XML attributes.
+#[derive(Debug, Deserialize)]
+struct Response {
+    #[serde(alias = "@status")]
+    status: String,
+    error: Option<Error>,
+    #[serde(rename = "nowPlaying")]
+    now_playing: Option<NowPlaying>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Error {
+    #[serde(alias = "@code")]
+    code: u32,
+    #[serde(alias = "@message", default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NowPlaying {
+    #[serde(default)]
+    entry: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(alias = "@id")]
+    id: String,
+    #[serde(alias = "@title", default)]
+    title: String,
+    #[serde(alias = "@artist", default)]
+    artist: String,
+    #[serde(alias = "@duration")]
+    duration: Option<u64>,
+    #[serde(rename = "minutesAgo", alias = "@minutesAgo", default)]
+    minutes_ago: u64,
+    #[serde(alias = "@username", default)]
+    username: String,
+}
+
+/// Parses a `getNowPlaying` response body, either `f=json` or the default XML.
+fn parse_now_playing(body: &str) -> anyhow::Result<Vec<Entry>> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        #[serde(rename = "subsonic-response")]
+        response: Response,
+    }
+
+    let response = if body.trim_start().starts_with('<') {
+        quick_xml::de::from_str::<Response>(body).context("error parsing xml response")?
+    } else {
+        serde_json::from_str::<Envelope>(body)
+            .context("error parsing json response")?
+            .response
+    };
+
+    if response.status != "ok" {
+        let error = response.error.context("failed response without error")?;
+        anyhow::bail!("subsonic error {}: {}", error.code, error.message);
+    }
+
+    Ok(response
+        .now_playing
+        .map(|now_playing| now_playing.entry)
+        .unwrap_or_default())
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    url: String,
+    username: String,
+    password: String,
+    // id of the current entry and the estimated moment it started playing
+    started: Option<(String, Instant)>,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, url: String, username: String, password: String) -> Self {
+        Self {
+            client,
+            url,
+            username,
+            password,
+            started: None,
+        }
+    }
+
+    pub async fn get_current_track(&mut self) -> anyhow::Result<Option<Track>> {
+        let salt: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(12)
+            .map(char::from)
+            .collect();
+        let token = format!("{:x}", md5::compute(format!("{}{salt}", self.password)));
+
+        let body = self
+            .client
+            .get(format!("{}/rest/getNowPlaying.view", self.url.trim_end_matches('/')))
+            .query(&[
+                ("u", self.username.as_str()),
+                ("t", &token),
+                ("s", &salt),
+                ("v", API_VERSION),
+                ("c", CLIENT_NAME),
+                ("f", "json"),
+            ])
+            .send()
+            .await
+            .context("error doing request")?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let entry = parse_now_playing(&body)?
+            .into_iter()
+            .filter(|entry| entry.username == self.username)
+            .min_by_key(|entry| entry.minutes_ago);
+
+        let Some(entry) = entry else {
+            self.started = None;
+            return Ok(None);
+        };
+
+        Ok(Some(self.estimate(entry, Instant::now())))
+    }
+
+    /// Subsonic only reports how many whole minutes ago the entry started, so the
+    /// start is estimated when the entry is first seen and the progress is counted from it.
+    fn estimate(&mut self, entry: Entry, now: Instant) -> Track {
+        let started = match &self.started {
+            Some((id, started)) if *id == entry.id => *started,
+            _ => {
+                let started = now
+                    .checked_sub(Duration::from_secs(entry.minutes_ago * 60))
+                    .unwrap_or(now);
+                self.started = Some((entry.id, started));
+                started
+            }
+        };
+
+        let duration = entry.duration.map(Duration::from_secs);
+        let progress = now.duration_since(started);
+
+        Track {
+            artist: entry.artist,
+            title: entry.title,
+            progress: duration.map_or(progress, |duration| progress.min(duration)),
+            duration,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, thread};
+
+    use tiny_http::{Response, Server};
+
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<subsonic-response xmlns="http://subsonic.org/restapi" status="ok" version="1.16.1">
+    <nowPlaying>
+        <entry id="11" parent="10" isDir="false" title="Song" album="Album" artist="Artist" duration="240" minutesAgo="2" username="me" playerId="1"/>
+        <entry id="21" parent="20" isDir="false" title="Other" artist="Someone" minutesAgo="0" username="friend" playerId="2"/>
+    </nowPlaying>
+</subsonic-response>"#;
+
+    const JSON: &str = r#"{"subsonic-response": {"status": "ok", "version": "1.16.1", "nowPlaying": {"entry": [
+        {"id": "11", "isDir": false, "title": "Song", "artist": "Artist", "duration": 240, "minutesAgo": 2, "username": "me", "playerId": 1},
+        {"id": "21", "isDir": false, "title": "Other", "artist": "Someone", "minutesAgo": 0, "username": "friend", "playerId": 2}
+    ]}}}"#;
+
+    fn entry(id: &str, duration: Option<u64>, minutes_ago: u64) -> Entry {
+        Entry {
+            id: id.to_string(),
+            title: "Song".to_string(),
+            artist: "Artist".to_string(),
+            duration,
+            minutes_ago,
+            username: "me".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_xml_and_json() {
+        for body in [XML, JSON] {
+            let entries = parse_now_playing(body).unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].id, "11");
+            assert_eq!(entries[0].title, "Song");
+            assert_eq!(entries[0].artist, "Artist");
+            assert_eq!(entries[0].duration, Some(240));
+            assert_eq!(entries[0].minutes_ago, 2);
+            assert_eq!(entries[0].username, "me");
+            assert_eq!(entries[1].duration, None);
+            assert_eq!(entries[1].username, "friend");
+        }
+    }
+
+    #[test]
+    fn parses_empty_now_playing() {
+        let xml = r#"<subsonic-response xmlns="http://subsonic.org/restapi" status="ok" version="1.16.1"><nowPlaying/></subsonic-response>"#;
+        let json = r#"{"subsonic-response": {"status": "ok", "version": "1.16.1", "nowPlaying": {}}}"#;
+        for body in [xml, json] {
+            assert!(parse_now_playing(body).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn reports_failed_status() {
+        let xml = r#"<subsonic-response xmlns="http://subsonic.org/restapi" status="failed" version="1.16.1">
+    <error code="40" message="Wrong username or password"/>
+</subsonic-response>"#;
+        let json = r#"{"subsonic-response": {"status": "failed", "version": "1.16.1",
+            "error": {"code": 40, "message": "Wrong username or password"}}}"#;
+        for body in [xml, json] {
+            let error = parse_now_playing(body).unwrap_err();
+            assert_eq!(error.to_string(), "subsonic error 40: Wrong username or password");
+        }
+    }
+
+    #[test]
+    fn estimates_progress_from_first_sighting() {
+        let mut client = Client::new(reqwest::Client::new(), String::new(), "me".to_string(), String::new());
+        let now = Instant::now() + Duration::from_secs(60 * 60);
+
+        let track = client.estimate(entry("11", Some(240), 2), now);
+        assert_eq!(track.progress, Duration::from_secs(120));
+        assert_eq!(track.duration, Some(Duration::from_secs(240)));
+
+        // still the same entry, minutesAgo is ignored and the progress keeps counting
+        let track = client.estimate(entry("11", Some(240), 3), now + Duration::from_secs(30));
+        assert_eq!(track.progress, Duration::from_secs(150));
+
+        let track = client.estimate(entry("11", Some(240), 4), now + Duration::from_secs(200));
+        assert_eq!(track.progress, Duration::from_secs(240));
+
+        let track = client.estimate(entry("12", None, 0), now + Duration::from_secs(300));
+        assert_eq!(track.progress, Duration::ZERO);
+        assert_eq!(track.duration, None);
+
+        let track = client.estimate(entry("12", None, 0), now + Duration::from_secs(1000));
+        assert_eq!(track.progress, Duration::from_secs(700));
+    }
+
+    #[tokio::test]
+    async fn picks_entry_of_user() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr());
+        let handle = thread::spawn(move || {
+            let mut auth = Vec::new();
+            for _ in 0..2 {
+                let request = server.recv().unwrap();
+                let url = request.url().to_string();
+                request.respond(Response::from_string(JSON)).unwrap();
+                assert!(url.starts_with("/rest/getNowPlaying.view?"), "{url}");
+
+                let query: HashMap<String, String> = reqwest::Url::parse(&format!("http://localhost{url}"))
+                    .unwrap()
+                    .query_pairs()
+                    .into_owned()
+                    .collect();
+                assert_eq!(query["f"], "json");
+                auth.push((query["u"].clone(), query["t"].clone(), query["s"].clone()));
+            }
+            auth
+        });
+
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url.clone(),
+            "me".to_string(),
+            "secret".to_string(),
+        );
+        let track = client.get_current_track().await.unwrap().unwrap();
+        assert_eq!(track.title, "Song");
+        assert_eq!(track.progress, Duration::from_secs(120));
+
+        let mut client = Client::new(reqwest::Client::new(), url, "nobody".to_string(), "secret".to_string());
+        assert!(client.get_current_track().await.unwrap().is_none());
+
+        let auth = handle.join().unwrap();
+        assert_eq!(auth[0].0, "me");
+        assert_eq!(auth[1].0, "nobody");
+        for (_, token, salt) in &auth {
+            assert_eq!(*token, format!("{:x}", md5::compute(format!("secret{salt}"))));
+        }
+        assert_ne!(auth[0].2, auth[1].2, "salt is reused");
+    }
+}
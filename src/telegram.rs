@@ -1,27 +1,42 @@
-use std::{io::BufRead, sync::LazyLock, time::Duration};
+use std::{fmt, fs, io::BufRead, path::Path, time::Duration};
 
 use anyhow::Context;
 use async_trait::async_trait;
-use grammers_client::{Client, FixedReconnect, ReconnectionPolicy, SignInError};
+use grammers_client::{Client, FixedReconnect, InvocationError, ReconnectionPolicy, SignInError};
+use grammers_mtsender::RpcError;
 use grammers_session::Session;
-use grammers_tl_types::functions::account::UpdateProfile;
+use grammers_tl_types::{
+    enums::{self, InputUser},
+    functions::{account::UpdateProfile, users::GetFullUser},
+};
 use serde::Deserialize;
 use serde_json::json;
 
-static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+use crate::proxy::{ProxyConfig, ProxyKind};
 
 static RECONNECTION_POLICY: &'static dyn ReconnectionPolicy = &FixedReconnect {
     attempts: 10,
     delay: Duration::from_secs(1),
 };
 
-pub async fn create_client(api_id: i32, api_hash: String) -> anyhow::Result<Client> {
+pub async fn create_client(api_id: i32, api_hash: String, proxy: Option<&ProxyConfig>) -> anyhow::Result<Client> {
+    let proxy_url = match proxy {
+        Some(proxy) if proxy.kind != ProxyKind::Socks5 => {
+            anyhow::bail!("only socks5 proxies are supported for mtproto")
+        }
+        Some(proxy) => Some(proxy.mtproto_url()?),
+        None => None,
+    };
+
     Ok(Client::connect(grammers_client::Config {
         session: Session::load_file_or_create("session.bin")?,
         api_id,
         api_hash,
         params: grammers_client::InitParams {
             reconnection_policy: RECONNECTION_POLICY,
+            // flood waits are handled per updater instead of blocking the whole loop
+            flood_sleep_threshold: 0,
+            proxy_url,
             ..Default::default()
         },
     })
@@ -69,19 +84,51 @@ pub async fn authorize(client: &Client) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Telegram refused the request and asked to wait before the next one.
+#[derive(Debug)]
+pub struct FloodWait(pub Duration);
+
+impl fmt::Display for FloodWait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "too many requests, retry after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for FloodWait {}
+
 pub async fn update_bio(client: &Client, bio: String) -> anyhow::Result<()> {
-    client
+    let result = client
         .invoke(&UpdateProfile {
             about: Some(bio),
             first_name: None,
             last_name: None,
         })
-        .await?;
+        .await;
 
-    Ok(())
+    match result {
+        Ok(_) => Ok(()),
+        // FLOOD_WAIT_X and FLOOD_PREMIUM_WAIT_X, X being the seconds to wait
+        Err(InvocationError::Rpc(RpcError {
+            name,
+            value: Some(seconds),
+            ..
+        })) if name.starts_with("FLOOD") => Err(FloodWait(Duration::from_secs(seconds.into())).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_bio(client: &Client) -> anyhow::Result<String> {
+    let enums::users::UserFull::Full(full) = client
+        .invoke(&GetFullUser {
+            id: InputUser::UserSelf,
+        })
+        .await?;
+    let enums::UserFull::Full(user) = full.full_user;
+    Ok(user.about.unwrap_or_default())
 }
 
 pub async fn update_channel_message(
+    client: &reqwest::Client,
     token: String,
     channel_id: i64,
     message_id: i64,
@@ -90,9 +137,16 @@ pub async fn update_channel_message(
     #[derive(Deserialize)]
     struct Response {
         ok: bool,
+        description: Option<String>,
+        parameters: Option<Parameters>,
+    }
+
+    #[derive(Deserialize)]
+    struct Parameters {
+        retry_after: Option<u64>,
     }
 
-    let response = CLIENT
+    let response = client
         .post(format!("https://api.telegram.org/bot{}/editMessageText", token))
         .json(&json!({
             "chat_id": channel_id,
@@ -105,26 +159,95 @@ pub async fn update_channel_message(
         .await?;
 
     if !response.ok {
-        anyhow::bail!("not ok");
+        if let Some(retry_after) = response.parameters.and_then(|parameters| parameters.retry_after) {
+            return Err(FloodWait(Duration::from_secs(retry_after)).into());
+        }
+        anyhow::bail!("not ok: {}", response.description.unwrap_or_default());
     }
     Ok(())
 }
 
+const BIO_MAX_LENGTH: usize = 70;
+const PREMIUM_BIO_MAX_LENGTH: usize = 140;
+const MESSAGE_MAX_LENGTH: usize = 4096;
+
 #[async_trait]
-pub trait Updater {
+pub trait Updater: Send + Sync {
     async fn update(&self, text: String) -> anyhow::Result<()>;
+
+    /// The longest text Telegram accepts for this updater.
+    fn max_length(&self) -> usize;
+
+    /// Text that was there before the first update, rendered as `{original}`.
+    fn original(&self) -> Option<&str> {
+        None
+    }
+
+    /// Puts back whatever was there before the first update, called on shutdown.
+    async fn restore(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the bio from before the first update is kept until it is restored.
+/// If the file is still there on startup, the previous run didn't shut down
+/// cleanly and the current bio is probably a track, so the saved one wins.
+const ORIGINAL_BIO_PATH: &str = "original_bio.txt";
+
+pub struct BioUpdater {
+    client: Client,
+    premium: bool,
+    original: String,
 }
 
-pub struct BioUpdater(pub Client);
+impl BioUpdater {
+    pub async fn new(client: Client) -> anyhow::Result<Self> {
+        let me = client.get_me().await?;
+
+        let original = if Path::new(ORIGINAL_BIO_PATH).exists() {
+            log::info!("previous run did not restore the bio, using the saved original");
+            fs::read_to_string(ORIGINAL_BIO_PATH).context("error reading original bio")?
+        } else {
+            let bio = get_bio(&client).await.context("error getting bio")?;
+            fs::write(ORIGINAL_BIO_PATH, &bio).context("error saving original bio")?;
+            bio
+        };
+
+        Ok(Self {
+            client,
+            premium: me.raw.premium,
+            original,
+        })
+    }
+}
 
 #[async_trait]
 impl Updater for BioUpdater {
     async fn update(&self, text: String) -> anyhow::Result<()> {
-        update_bio(&self.0, text).await
+        update_bio(&self.client, text).await
+    }
+
+    fn max_length(&self) -> usize {
+        if self.premium {
+            PREMIUM_BIO_MAX_LENGTH
+        } else {
+            BIO_MAX_LENGTH
+        }
+    }
+
+    fn original(&self) -> Option<&str> {
+        Some(&self.original)
+    }
+
+    async fn restore(&self) -> anyhow::Result<()> {
+        update_bio(&self.client, self.original.clone()).await?;
+        fs::remove_file(ORIGINAL_BIO_PATH).context("error removing saved original bio")?;
+        Ok(())
     }
 }
 
 pub struct ChannelUpdater {
+    pub client: reqwest::Client,
     pub token: String,
     pub channel_id: i64,
     pub message_id: i64,
@@ -133,6 +256,10 @@ pub struct ChannelUpdater {
 #[async_trait]
 impl Updater for ChannelUpdater {
     async fn update(&self, text: String) -> anyhow::Result<()> {
-        update_channel_message(self.token.clone(), self.channel_id, self.message_id, text).await
+        update_channel_message(&self.client, self.token.clone(), self.channel_id, self.message_id, text).await
+    }
+
+    fn max_length(&self) -> usize {
+        MESSAGE_MAX_LENGTH
     }
 }
@@ -0,0 +1,646 @@
+use std::{fmt, time::Duration};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::UnifiedTrack;
+
+const VARIABLES: &[&str] = &[
+    "artist",
+    "title",
+    "kind",
+    "album",
+    "album_artist",
+    "year",
+    "url",
+    "cover_url",
+    "id",
+    "explicit",
+    "source",
+    "progress",
+    "duration",
+    "remaining",
+    "percent",
+    "bar",
+    "elapsed_bar",
+    "original",
+];
+
+fn default_bar_width() -> usize {
+    10
+}
+
+fn default_bar_filled() -> String {
+    "▰".to_string()
+}
+
+fn default_bar_empty() -> String {
+    "▱".to_string()
+}
+
+/// Looks of the `{bar}` progress bar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    #[serde(default = "default_bar_width")]
+    pub width: usize,
+    #[serde(default = "default_bar_filled")]
+    pub filled: String,
+    #[serde(default = "default_bar_empty")]
+    pub empty: String,
+}
+
+impl Default for Bar {
+    fn default() -> Self {
+        Self {
+            width: default_bar_width(),
+            filled: default_bar_filled(),
+            empty: default_bar_empty(),
+        }
+    }
+}
+
+impl Bar {
+    fn render(&self, ratio: f64) -> String {
+        let filled = ((ratio * self.width as f64).round() as usize).min(self.width);
+        self.filled.repeat(filled) + &self.empty.repeat(self.width - filled)
+    }
+}
+
+/// Everything besides the track that variables are rendered from.
+pub struct RenderContext<'a> {
+    pub bar: &'a Bar,
+    /// The bio from before the first update, only known to the bio updater.
+    pub original: Option<&'a str>,
+}
+
+/// How far into the track playback is, `None` when the duration is unknown or zero.
+fn ratio(track: &UnifiedTrack) -> Option<f64> {
+    let duration = track.duration.filter(|duration| !duration.is_zero())?;
+    Some((track.progress?.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let total_seconds = duration.as_secs();
+    let minutes = total_seconds / 60;
+    let seconds = total_seconds % 60;
+    format!("{minutes}:{seconds:02}")
+}
+
+/// Value of a template variable, `None` when the source doesn't know it.
+fn variable(track: &UnifiedTrack, context: &RenderContext, name: &str) -> Option<String> {
+    let value = match name {
+        "artist" => track.artist.clone(),
+        "title" => track.title.clone(),
+        "kind" => track.kind.as_str().to_string(),
+        "album" => track.album.clone()?,
+        "album_artist" => track.album_artist.clone()?,
+        "year" => track.year?.to_string(),
+        "url" => track.url.clone()?,
+        "cover_url" => track.cover_url.clone()?,
+        "id" => track.id.clone()?,
+        "explicit" => track.explicit.then(|| "🅴".to_string())?,
+        "source" => track.source.to_string(),
+        "progress" => format_duration(track.progress?),
+        "duration" => format_duration(track.duration?),
+        "remaining" => format_duration(
+            track
+                .duration
+                .filter(|duration| !duration.is_zero())?
+                .saturating_sub(track.progress?),
+        ),
+        "percent" => format!("{}", (ratio(track)? * 100.0) as u8),
+        "bar" => context.bar.render(ratio(track)?),
+        "elapsed_bar" => format!(
+            "{} {}",
+            context.bar.render(ratio(track)?),
+            format_duration(track.progress?)
+        ),
+        "original" => context.original?.to_string(),
+        _ => unreachable!("unknown variable {name}"),
+    };
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// What an unknown variable renders as when no `default` filter is given.
+fn fallback(name: &str) -> &'static str {
+    match name {
+        "progress" | "duration" | "remaining" => "--:--",
+        _ => "",
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Upper,
+    Lower,
+    /// The first name of a comma-separated list, e.g. of artists.
+    First,
+    /// Shortens the value to the given number of characters, ending it with an ellipsis.
+    Truncate(usize),
+    Default(String),
+}
+
+impl Filter {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let (name, argument) = match s.split_once(':') {
+            Some((name, argument)) => (name.trim(), Some(argument)),
+            None => (s.trim(), None),
+        };
+
+        match (name, argument) {
+            ("upper", None) => Ok(Self::Upper),
+            ("lower", None) => Ok(Self::Lower),
+            ("first", None) => Ok(Self::First),
+            ("truncate", Some(length)) => {
+                let length = length
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("invalid truncate length `{}`", length.trim()))?;
+                if length == 0 {
+                    anyhow::bail!("truncate length must be positive");
+                }
+                Ok(Self::Truncate(length))
+            }
+            ("default", Some(text)) => Ok(Self::Default(text.to_string())),
+            ("truncate" | "default", None) => anyhow::bail!("filter `{name}` needs an argument, e.g. `{name}:...`"),
+            ("upper" | "lower" | "first", Some(_)) => anyhow::bail!("filter `{name}` takes no argument"),
+            _ => anyhow::bail!("unknown filter `{name}`"),
+        }
+    }
+
+    fn apply(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Self::Upper => value.map(|value| value.to_uppercase()),
+            Self::Lower => value.map(|value| value.to_lowercase()),
+            Self::First => value.map(|value| value.split(", ").next().unwrap_or_default().to_string()),
+            Self::Truncate(length) => value.map(|value| truncate(&value, *length)),
+            Self::Default(text) => value.or_else(|| Some(text.clone())),
+        }
+    }
+}
+
+/// Cuts `s` down to `length` characters, the last of which becomes an ellipsis.
+pub fn truncate(s: &str, length: usize) -> String {
+    if s.chars().count() <= length {
+        return s.to_string();
+    }
+    let mut truncated: String = s.chars().take(length.saturating_sub(1)).collect();
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push('…');
+    truncated
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Variable {
+        name: String,
+        filters: Vec<Filter>,
+    },
+    If {
+        condition: Condition,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// The test of an `{% if %}` block: whether the variable is known, or with
+/// `value` whether it equals that text.
+#[derive(Debug, Clone)]
+struct Condition {
+    name: String,
+    value: Option<String>,
+    negate: bool,
+}
+
+impl Condition {
+    /// Parses what follows `if`: `name`, `not name`, `name == "value"` or `name != "value"`.
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let (left, value, negate) = if let Some((left, value)) = s.split_once("==") {
+            (left, Some(value), false)
+        } else if let Some((left, value)) = s.split_once("!=") {
+            (left, Some(value), true)
+        } else if let Some(name) = s.trim().strip_prefix("not ") {
+            (name, None, true)
+        } else {
+            (s, None, false)
+        };
+
+        let name = left.trim();
+        check_variable(name)?;
+        let value = value
+            .map(|value| {
+                let value = value.trim();
+                value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .filter(|_| value.len() >= 2)
+                    .map(str::to_string)
+                    .with_context(|| format!("expected a quoted value like `\"text\"`, got `{value}`"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            value,
+            negate,
+        })
+    }
+
+    fn test(&self, track: &UnifiedTrack, context: &RenderContext) -> bool {
+        let variable = variable(track, context, &self.name);
+        let matches = match &self.value {
+            Some(value) => variable.as_ref() == Some(value),
+            None => variable.is_some(),
+        };
+        matches != self.negate
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.value, self.negate) {
+            (Some(value), false) => write!(f, "{} == \"{value}\"", self.name),
+            (Some(value), true) => write!(f, "{} != \"{value}\"", self.name),
+            (None, false) => write!(f, "{}", self.name),
+            (None, true) => write!(f, "not {}", self.name),
+        }
+    }
+}
+
+fn check_variable(name: &str) -> anyhow::Result<()> {
+    if !VARIABLES.contains(&name) {
+        anyhow::bail!("unknown variable `{name}`, expected one of: {}", VARIABLES.join(", "));
+    }
+    Ok(())
+}
+
+/// An open `{% if %}` block while parsing.
+struct Block {
+    condition: Condition,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+fn parse(source: &str) -> anyhow::Result<Vec<Node>> {
+    let mut root = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+
+    fn current<'a>(root: &'a mut Vec<Node>, blocks: &'a mut [Block]) -> &'a mut Vec<Node> {
+        match blocks.last_mut() {
+            Some(block) => block.otherwise.as_mut().unwrap_or(&mut block.then),
+            None => root,
+        }
+    }
+
+    let mut rest = source;
+    while !rest.is_empty() {
+        let position = source.len() - rest.len();
+
+        if let Some(tag) = rest.strip_prefix("{%") {
+            let end = tag
+                .find("%}")
+                .with_context(|| format!("unclosed `{{%` at position {position}"))?;
+            let content = tag[..end].trim();
+            rest = &tag[end + 2..];
+
+            match content.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["if", ..] => {
+                    let condition =
+                        Condition::parse(&content[2..]).with_context(|| format!("at position {position}"))?;
+                    blocks.push(Block {
+                        condition,
+                        then: Vec::new(),
+                        otherwise: None,
+                    });
+                }
+                ["else"] => {
+                    let block = blocks
+                        .last_mut()
+                        .with_context(|| format!("`else` without `if` at position {position}"))?;
+                    if block.otherwise.is_some() {
+                        anyhow::bail!("second `else` in the same `if` at position {position}");
+                    }
+                    block.otherwise = Some(Vec::new());
+                }
+                ["endif"] => {
+                    let block = blocks
+                        .pop()
+                        .with_context(|| format!("`endif` without `if` at position {position}"))?;
+                    current(&mut root, &mut blocks).push(Node::If {
+                        condition: block.condition,
+                        then: block.then,
+                        otherwise: block.otherwise.unwrap_or_default(),
+                    });
+                }
+                _ => anyhow::bail!(
+                    "unknown tag `{{%{}%}}` at position {position}, expected `if`, `else` or `endif`",
+                    &tag[..end]
+                ),
+            }
+        } else if let Some(expression) = rest.strip_prefix('{') {
+            let end = expression
+                .find('}')
+                .with_context(|| format!("unclosed `{{` at position {position}"))?;
+            let mut parts = expression[..end].split('|');
+            rest = &expression[end + 1..];
+
+            let name = parts.next().unwrap_or_default().trim();
+            check_variable(name).with_context(|| format!("at position {position}"))?;
+            let filters = parts
+                .map(Filter::parse)
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("in `{{{name}}}` at position {position}"))?;
+
+            current(&mut root, &mut blocks).push(Node::Variable {
+                name: name.to_string(),
+                filters,
+            });
+        } else {
+            let end = rest.find('{').unwrap_or(rest.len());
+            current(&mut root, &mut blocks).push(Node::Text(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+
+    if let Some(block) = blocks.last() {
+        anyhow::bail!("`if {}` is never closed with `endif`", block.condition);
+    }
+
+    Ok(root)
+}
+
+fn render_nodes(nodes: &[Node], track: &UnifiedTrack, context: &RenderContext, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable { name, filters } => {
+                let value = filters
+                    .iter()
+                    .fold(variable(track, context, name), |value, filter| filter.apply(value));
+                output.push_str(value.as_deref().unwrap_or(fallback(name)));
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if condition.test(track, context) {
+                    render_nodes(then, track, context, output);
+                } else {
+                    render_nodes(otherwise, track, context, output);
+                }
+            }
+        }
+    }
+}
+
+/// A parsed message template. Besides `{variable}` it supports filters like
+/// `{title|truncate:30}` and `{% if album %}...{% else %}...{% endif %}` blocks,
+/// whose condition may also compare a variable, as in `{% if kind == "episode" %}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            source: source.to_string(),
+            nodes: parse(source)?,
+        })
+    }
+
+    pub fn render(&self, track: &UnifiedTrack, context: &RenderContext) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, track, context, &mut output);
+        output
+    }
+
+    /// Renders the template into at most `max_length` characters. The least
+    /// important fields go first: the album is dropped, then the title is
+    /// truncated, then artists are removed from the end of the list. Text that
+    /// doesn't come from the track, like `{original}`, is kept as long as
+    /// possible. Whatever still doesn't fit is cut off.
+    pub fn render_within(&self, track: &UnifiedTrack, context: &RenderContext, max_length: usize) -> String {
+        let overflow = |text: &str| text.chars().count().saturating_sub(max_length);
+
+        let mut track = track.clone();
+        let mut text = self.render(&track, context);
+        if overflow(&text) == 0 {
+            return text;
+        }
+
+        if track.album.take().is_some() {
+            text = self.render(&track, context);
+        }
+
+        while overflow(&text) > 0 && track.title.chars().count() > 1 {
+            let length = track.title.chars().count().saturating_sub(overflow(&text)).max(1);
+            track.title = truncate(&track.title, length);
+            text = self.render(&track, context);
+        }
+
+        while overflow(&text) > 0 {
+            match track.artist.rsplit_once(", ") {
+                Some((rest, _)) => track.artist = rest.to_string(),
+                None if track.artist.chars().count() > 1 => {
+                    let length = track.artist.chars().count().saturating_sub(overflow(&text)).max(1);
+                    track.artist = truncate(&track.artist, length);
+                }
+                None => break,
+            }
+            text = self.render(&track, context);
+        }
+
+        truncate(&text, max_length)
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source).map_err(|e| format!("invalid template {source:?}: {e:#}"))
+    }
+}
+
+impl From<Template> for String {
+    fn from(val: Template) -> Self {
+        val.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrackKind;
+
+    fn track() -> UnifiedTrack {
+        UnifiedTrack {
+            artist: "Artist".to_string(),
+            title: "Title".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn render(source: &str, track: &UnifiedTrack) -> String {
+        let context = RenderContext {
+            bar: &Bar::default(),
+            original: None,
+        };
+        Template::parse(source).unwrap().render(track, &context)
+    }
+
+    fn error(source: &str) -> String {
+        format!("{:#}", Template::parse(source).unwrap_err())
+    }
+
+    #[test]
+    fn compares_variable_to_value() {
+        let source = r#"{% if kind == "episode" %}🎙{% else %}🎵{% endif %} {title}"#;
+        let episode = UnifiedTrack {
+            kind: TrackKind::Episode,
+            ..track()
+        };
+        assert_eq!(render(source, &track()), "🎵 Title");
+        assert_eq!(render(source, &episode), "🎙 Title");
+
+        let source = r#"{%if kind!="episode"%}{artist} — {%endif%}{title}"#;
+        assert_eq!(render(source, &track()), "Artist — Title");
+        assert_eq!(render(source, &episode), "Title");
+    }
+
+    #[test]
+    fn unknown_variable_never_equals() {
+        assert_eq!(render(r#"{% if album == "" %}empty{% endif %}"#, &track()), "");
+        assert_eq!(
+            render(r#"{% if album != "Album" %}other{% endif %}"#, &track()),
+            "other"
+        );
+    }
+
+    #[test]
+    fn tests_whether_variable_is_known() {
+        let source = "{% if album %}{album}{% else %}single{% endif %}|{% if not album %}no album{% endif %}";
+        let with_album = UnifiedTrack {
+            album: Some("Album".to_string()),
+            ..track()
+        };
+        assert_eq!(render(source, &track()), "single|no album");
+        assert_eq!(render(source, &with_album), "Album|");
+    }
+
+    #[test]
+    fn reports_errors() {
+        for (source, message) in [
+            (
+                "{nope}",
+                "at position 0: unknown variable `nope`, expected one of: artist,",
+            ),
+            ("{% if nope %}{% endif %}", "at position 0: unknown variable `nope`"),
+            (
+                r#"{% if kind == episode %}{% endif %}"#,
+                "at position 0: expected a quoted value",
+            ),
+            (
+                r#"{% if kind == " %}{% endif %}"#,
+                "at position 0: expected a quoted value",
+            ),
+            (
+                r#"{% if kind == "episode" %}"#,
+                r#"`if kind == "episode"` is never closed with `endif`"#,
+            ),
+            ("{% if not album %}", "`if not album` is never closed with `endif`"),
+            ("a {% else %}", "`else` without `if` at position 2"),
+            ("{% endif %}", "`endif` without `if` at position 0"),
+            (
+                "{% if album %}{% else %}{% else %}{% endif %}",
+                "second `else` in the same `if` at position 24",
+            ),
+            ("{% for x %}", "unknown tag `{% for x %}` at position 0"),
+            ("{% if album", "unclosed `{%` at position 0"),
+            ("{title", "unclosed `{` at position 0"),
+            ("{title|shout}", "in `{title}` at position 0: unknown filter `shout`"),
+            (
+                "{title|truncate:0}",
+                "in `{title}` at position 0: truncate length must be positive",
+            ),
+            (
+                "{title|truncate:x}",
+                "in `{title}` at position 0: invalid truncate length `x`",
+            ),
+            (
+                "{title|default}",
+                "in `{title}` at position 0: filter `default` needs an argument",
+            ),
+            (
+                "{title|upper:x}",
+                "in `{title}` at position 0: filter `upper` takes no argument",
+            ),
+        ] {
+            let error = error(source);
+            assert!(error.starts_with(message), "{source:?} failed with {error:?}");
+        }
+    }
+
+    fn render_within(source: &str, track: &UnifiedTrack, original: Option<&str>, max_length: usize) -> String {
+        let context = RenderContext {
+            bar: &Bar::default(),
+            original,
+        };
+        Template::parse(source)
+            .unwrap()
+            .render_within(track, &context, max_length)
+    }
+
+    #[test]
+    fn shrinks_album_then_title_then_artists() {
+        let source = "{artist} — {title}{% if album %} ({album}){% endif %}";
+        let track = UnifiedTrack {
+            artist: "A1, A2".to_string(),
+            title: "Long Title Here".to_string(),
+            album: Some("Album".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_within(source, &track, None, 32),
+            "A1, A2 — Long Title Here (Album)"
+        );
+        assert_eq!(render_within(source, &track, None, 31), "A1, A2 — Long Title Here");
+        assert_eq!(render_within(source, &track, None, 20), "A1, A2 — Long Title…");
+        assert_eq!(render_within(source, &track, None, 10), "A1, A2 — …");
+        assert_eq!(render_within(source, &track, None, 9), "A1 — …");
+        assert_eq!(render_within(source, &track, None, 5), "… — …");
+    }
+
+    #[test]
+    fn shrinks_artists_without_title_in_template() {
+        let track = UnifiedTrack {
+            artist: "First, Second".to_string(),
+            ..track()
+        };
+
+        assert_eq!(render_within("🎧 {artist}", &track, None, 10), "🎧 First");
+        assert_eq!(render_within("🎧 {artist}", &track, None, 5), "🎧 Fi…");
+    }
+
+    #[test]
+    fn keeps_original_while_possible() {
+        let source = "{original} | {artist} — {title}";
+        let original = "My bio text here";
+
+        assert_eq!(
+            render_within(source, &track(), Some(original), 30),
+            "My bio text here | Artist — T…"
+        );
+        assert_eq!(
+            render_within(source, &track(), Some(original), 24),
+            "My bio text here | … — …"
+        );
+        assert_eq!(render_within(source, &track(), Some(original), 12), "My bio text…");
+    }
+}
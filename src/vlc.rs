@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::UnifiedTrack;
+
+pub const DEFAULT_URL: &str = "http://localhost:8080";
+
+#[derive(Debug)]
+pub struct Track {
+    pub state: State,
+    pub artist: String,
+    pub title: String,
+    pub progress: Duration,
+    pub duration: Option<Duration>,
+}
+
+impl From<Track> for UnifiedTrack {
+    fn from(val: Track) -> Self {
+        UnifiedTrack {
+            artist: val.artist,
+            title: val.title,
+            progress: Some(val.progress),
+            duration: val.duration,
+            paused: val.state != State::Playing,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum State {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+impl From<&str> for State {
+    fn from(s: &str) -> Self {
+        match s {
+            "playing" => Self::Playing,
+            "paused" => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+}
+
+/// Splits a file name like "Artist - Title.mp3" into the artist and the title.
+fn split_filename(filename: &str) -> (String, String) {
+    let stem = match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.contains(' ') => stem,
+        _ => filename,
+    };
+    match stem.split_once(" - ") {
+        Some((artist, title)) => (artist.trim().to_string(), title.trim().to_string()),
+        None => (String::new(), stem.trim().to_string()),
+    }
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    url: String,
+    password: Option<String>,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, url: String, password: Option<String>) -> Self {
+        Self { client, url, password }
+    }
+
+    pub async fn get_current_track(&self) -> anyhow::Result<Option<Track>> {
+        #[derive(Deserialize)]
+        struct Status {
+            state: String,
+            #[serde(default)]
+            time: u64,
+            #[serde(default)]
+            length: i64,
+            information: Option<Information>,
+        }
+
+        #[derive(Deserialize)]
+        struct Information {
+            category: Category,
+        }
+
+        #[derive(Deserialize)]
+        struct Category {
+            meta: Meta,
+        }
+
+        #[derive(Deserialize)]
+        struct Meta {
+            artist: Option<String>,
+            title: Option<String>,
+            filename: Option<String>,
+        }
+
+        let mut request = self
+            .client
+            .get(format!("{}/requests/status.json", self.url.trim_end_matches('/')));
+        // vlc only checks the password, the user name is always empty
+        if let Some(password) = &self.password {
+            request = request.basic_auth("", Some(password));
+        }
+
+        let status: Status = request
+            .send()
+            .await
+            .context("error doing request")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("error parsing status")?;
+
+        let state = State::from(status.state.as_str());
+        let Some(information) = status.information else {
+            return Ok(None);
+        };
+        if state == State::Stopped {
+            return Ok(None);
+        }
+
+        let meta = information.category.meta;
+        let (artist, title) = match (meta.artist, meta.title) {
+            (Some(artist), Some(title)) => (artist, title),
+            (artist, title) => {
+                let (file_artist, file_title) = split_filename(meta.filename.as_deref().unwrap_or_default());
+                (artist.unwrap_or(file_artist), title.unwrap_or(file_title))
+            }
+        };
+
+        Ok(Some(Track {
+            state,
+            artist,
+            title,
+            progress: Duration::from_secs(status.time),
+            // streams report a length of -1 or 0
+            duration: u64::try_from(status.length)
+                .ok()
+                .filter(|length| *length > 0)
+                .map(Duration::from_secs),
+        }))
+    }
+}